@@ -1,37 +1,161 @@
-use egui::RichText;
+use std::sync::Arc;
+
+use egui::{Key, KeyboardShortcut, Modifiers, RichText};
 use egui_extras::{Column, TableBuilder};
 use lopdf::Document;
 
 use crate::{
+    background::{Progress, Task},
+    history::History,
+    jobs::{FontScan, FontScanJob, GenerateOutlineJob, SaveJob},
+    outline_editor::OutlineEditor,
     pdf_tools::{
-        pdf_font::PdfFont, pdf_outline::PdfOutline, PdfFontReader, PdfOutlineGenerator,
-        PdfOutlineInserter,
+        load_document,
+        pdf_font::{FontStats, PdfFont},
+        pdf_outline::{
+            check_page_numbers, export_outline, import_outline, OutlineFormat, PdfOutline,
+        },
+        pdf_version, InsertMode, PdfError, PdfOutlineReader, PdfTextIndex,
     },
-    save_file::save_file_from_rust,
+    save_file::{file_name_of, save_file_from_rust},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum OutlineLevel {
-    First,
-    Second,
-    Third,
+    /// Zero-based depth in the outline
+    Heading(usize),
     None,
 }
 
+/// Number of outline levels offered before the user adds or removes any
+const DEFAULT_LEVEL_COUNT: usize = 3;
+
 #[derive(Debug)]
 struct FontRow {
     font: PdfFont,
-    count: usize,
+    stats: FontStats,
     level: OutlineLevel,
+    suggested_level: OutlineLevel,
+    /// How likely the font is to be a heading font, between 0 and 1
+    confidence: f32,
+}
+
+/// A column the font table can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FontColumn {
+    Name,
+    Size,
+    Pages,
+    Runs,
+    Glyphs,
+    FirstPage,
+    Heading,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FontSort {
+    column: FontColumn,
+    descending: bool,
+}
+
+impl Default for FontSort {
+    fn default() -> Self {
+        //largest fonts first, as headings usually are
+        Self {
+            column: FontColumn::Size,
+            descending: true,
+        }
+    }
+}
+
+/// Which rows of the font table are shown.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct FontFilter {
+    /// Part of the base font name, in any case
+    name: String,
+    min_size: f32,
+}
+
+impl FontFilter {
+    fn matches(&self, font: &PdfFont) -> bool {
+        font.size.0.max(font.size.1) >= self.min_size
+            && font
+                .base_font
+                .to_lowercase()
+                .contains(&self.name.trim().to_lowercase())
+    }
+
+    fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Work running in the background, one at a time.
+#[derive(Debug)]
+enum AppTask {
+    FontScan(Task<Result<FontScan, PdfError>>),
+    GenerateOutline(Task<PdfOutline>),
+    Save(Task<Result<Vec<u8>, PdfError>>),
+}
+
+impl AppTask {
+    fn status(&self) -> (&str, &Progress) {
+        match self {
+            AppTask::FontScan(task) => (task.description(), task.progress()),
+            AppTask::GenerateOutline(task) => (task.description(), task.progress()),
+            AppTask::Save(task) => (task.description(), task.progress()),
+        }
+    }
+}
+
+/// Everything undo and redo restore.
+#[derive(Clone, Debug, PartialEq)]
+struct EditState {
+    level_count: usize,
+    /// The level of each row of the font table
+    levels: Vec<OutlineLevel>,
+    outline: Option<PdfOutline>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct App {
     file_name: String,
     fonts: Option<Vec<FontRow>>,
-    heading_fonts: [Vec<PdfFont>; 3],
+    font_sort: FontSort,
+    font_filter: FontFilter,
+    level_count: usize,
+    heading_fonts: Vec<Vec<PdfFont>>,
     outline: Option<PdfOutline>,
-    doc: Option<Document>,
+    outline_editor: OutlineEditor,
+    insert_mode: InsertMode,
+    history: History<EditState>,
+    doc: Option<Arc<Document>>,
+    /// The text of every page, read once when the document is loaded
+    text_index: Option<Arc<PdfTextIndex>>,
+    task: Option<AppTask>,
+    /// Shown above the document until dismissed
+    errors: Vec<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            file_name: String::default(),
+            fonts: None,
+            font_sort: FontSort::default(),
+            font_filter: FontFilter::default(),
+            level_count: DEFAULT_LEVEL_COUNT,
+            heading_fonts: vec![vec![]; DEFAULT_LEVEL_COUNT],
+            outline: None,
+            outline_editor: OutlineEditor::new(0),
+            insert_mode: InsertMode::default(),
+            history: History::default(),
+            doc: None,
+            text_index: None,
+            task: None,
+            errors: vec![],
+        }
+    }
 }
 
 impl App {
@@ -47,50 +171,129 @@ impl App {
 impl eframe::App for App {
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.doc.is_none() {
-            self.check_for_new_pdf_file(ctx);
+        self.check_for_dropped_files(ctx);
+        self.poll_task(ctx);
+
+        //text fields handle these shortcuts themselves while being edited
+        if ctx.memory(|memory| memory.focus().is_none()) {
+            let undo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+            let redo = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+            if ctx.input_mut(|input| input.consume_shortcut(&redo)) {
+                self.redo();
+            } else if ctx.input_mut(|input| input.consume_shortcut(&undo)) {
+                self.undo();
+            }
         }
 
         egui::TopBottomPanel::top("Header").show(ctx, |ui| {
-            if self.doc.is_some() {
-                let mut string = String::from("Current File: ");
-                string.push_str(&self.file_name);
-                ui.heading(string);
-            } else {
-                ui.heading("Drag and Drop a PDF File");
-            }
+            ui.horizontal(|ui| {
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button(RichText::new("Open PDF…").heading()).clicked() {
+                    match crate::save_file::open_file_from_rust("PDF", &["pdf"]) {
+                        Ok(Some((name, bytes))) => self.load_pdf(ctx, name, &bytes),
+                        Ok(None) => (),
+                        Err(e) => self.report_error(e),
+                    }
+                }
+
+                if self.doc.is_some() {
+                    let mut string = String::from("Current File: ");
+                    string.push_str(&self.file_name);
+                    ui.heading(string);
+                } else {
+                    ui.heading("Drag and Drop a PDF File");
+                }
+            });
         });
+        self.error_panel(ctx);
 
         egui::TopBottomPanel::bottom("Buttons").show(ctx, |ui| {
             ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
-                let enabled =
-                    self.fonts.is_some() && self.heading_fonts.iter().any(|v| !v.is_empty());
+                let enabled = self.task.is_none()
+                    && self.text_index.is_some()
+                    && self.heading_fonts.iter().any(|v| !v.is_empty());
                 ui.add_enabled_ui(enabled, |ui| {
                     if ui
                         .button(RichText::new("Generate Outline").heading())
                         .clicked()
                     {
-                        let fonts = self.heading_fonts.to_vec();
-                        self.outline = Some(self.doc.as_ref().unwrap().generate_outline(&fonts));
+                        self.start_outline_generation(ctx);
                     }
                 });
-                let enabled = self.outline.is_some();
+                let enabled = self.task.is_none() && self.outline.is_some();
                 ui.add_enabled_ui(enabled, |ui| {
                     if ui
                         .button(RichText::new("Save PDF with Outline").heading())
                         .clicked()
                     {
-                        let mut new_doc = self.doc.as_ref().unwrap().clone();
-                        //todo: check this
-                        new_doc
-                            .insert_outline(self.outline.as_ref().unwrap())
-                            .unwrap();
-                        let mut data = vec![];
-                        //todo: check this
-                        new_doc.save_to(&mut data).unwrap();
-                        save_file_from_rust(data);
+                        self.start_save(ctx);
                     }
                 });
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.add_enabled_ui(self.doc.is_some() && !self.is_generating_outline(), |ui| {
+                    if ui
+                        .button(RichText::new("Import Outline…").heading())
+                        .clicked()
+                    {
+                        let extensions = ["txt", "json", "yaml", "yml"];
+                        match crate::save_file::open_file_from_rust("Outline", &extensions) {
+                            Ok(Some((name, bytes))) => self.import_outline_file(&name, &bytes),
+                            Ok(None) => (),
+                            Err(e) => self.report_error(e),
+                        }
+                    }
+                });
+                ui.add_enabled_ui(enabled, |ui| {
+                    ui.menu_button(RichText::new("Export Outline").heading(), |ui| {
+                        for format in [
+                            OutlineFormat::Text,
+                            OutlineFormat::Json,
+                            OutlineFormat::Yaml,
+                            OutlineFormat::Pdftk,
+                            OutlineFormat::Qpdf,
+                        ] {
+                            if ui.button(format!("{:?}", format)).clicked() {
+                                ui.close_menu();
+                                self.export_outline(format);
+                            }
+                        }
+                    });
+                });
+
+                if ui
+                    .add_enabled(self.history.can_undo(), egui::Button::new("Undo"))
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(self.history.can_redo(), egui::Button::new("Redo"))
+                    .on_hover_text("Ctrl+Shift+Z")
+                    .clicked()
+                {
+                    self.redo();
+                }
+
+                let has_outline = self.doc.as_ref().map_or(false, |doc| {
+                    doc.catalog()
+                        .map_or(false, |catalog| catalog.has(b"Outlines"))
+                });
+                if has_outline {
+                    egui::ComboBox::from_label("Existing Outline")
+                        .selected_text(format!("{:?}", self.insert_mode))
+                        .show_ui(ui, |ui| {
+                            for mode in [InsertMode::Replace, InsertMode::Append, InsertMode::Merge]
+                            {
+                                ui.selectable_value(
+                                    &mut self.insert_mode,
+                                    mode,
+                                    format!("{:?}", mode),
+                                );
+                            }
+                        });
+                }
+                self.task_progress(ui);
             });
         });
 
@@ -100,13 +303,28 @@ impl eframe::App for App {
                     self.font_table(ui);
                 });
                 ui.vertical(|ui| {
-                    if let Some(outline) = &self.outline {
-                        ui.heading("Outline Preview");
+                    if self.doc.is_some() {
+                        ui.heading("Outline");
+                        ui.weak("Drop a .txt, .json or .yaml outline here to import it");
 
                         egui::ScrollArea::vertical()
                             .id_source("Outline Scroll Area")
                             .show(ui, |ui| {
-                                Self::outline_preview(ui, outline, 0);
+                                //the generated outline will replace the one being shown
+                                let generating = self.is_generating_outline();
+                                let had_outline = self.outline.is_some();
+                                let mut outline = self.outline.take().unwrap_or_default();
+                                let changed = ui
+                                    .add_enabled_ui(!generating, |ui| {
+                                        self.outline_editor.show(ui, &mut outline)
+                                    })
+                                    .inner;
+                                //an outline the user emptied is kept, saving it removes
+                                //the document's bookmarks
+                                self.outline = (had_outline || changed).then_some(outline);
+                                if changed {
+                                    self.history.record(self.edit_state());
+                                }
                             });
                     }
                 });
@@ -116,67 +334,219 @@ impl eframe::App for App {
 }
 
 impl App {
-    fn check_for_new_pdf_file(&mut self, ctx: &egui::Context) {
-        ctx.input(|input| {
-            for file in &input.raw.dropped_files {
-                if file.mime.ends_with("pdf") {
-                    if let Some(bytes) = &file.bytes {
-                        if let Ok(doc) = Document::load_mem(bytes) {
-                            self.doc = Some(doc);
-                            self.file_name = file.name.clone();
-                        }
+    /// Loads a dropped PDF if none is open yet, and imports dropped outline files into an open one.
+    fn check_for_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped_files = ctx.input(|input| input.raw.dropped_files.clone());
+        for file in dropped_files {
+            let name = match &file.path {
+                Some(path) => file_name_of(path),
+                None => file.name.clone(),
+            };
+            // The web backend provides the contents, native backends only the path
+            let bytes = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => bytes.to_vec(),
+                (None, Some(path)) => match std::fs::read(path) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        self.report_error(format!("Failed to read {}: {}", path.display(), e));
+                        continue;
                     }
+                },
+                (None, None) => {
+                    self.report_error(format!("Failed to read {}: it has no contents", name));
+                    continue;
                 }
+            };
+
+            //the header tells PDFs apart, the MIME type and extension can't be relied on
+            let is_pdf = pdf_version(&bytes).is_some();
+            let is_outline = name.rsplit_once('.').map_or(false, |(_, extension)| {
+                extension.parse::<OutlineFormat>().is_ok()
+            });
+            match &self.doc {
+                None if is_outline && !is_pdf => self.report_error(format!(
+                    "Failed to import {}: open a PDF before importing an outline",
+                    name
+                )),
+                None => self.load_pdf(ctx, name, &bytes),
+                Some(_) if is_pdf => self.report_error(format!(
+                    "Failed to open {}: {} is already open",
+                    name, self.file_name
+                )),
+                Some(_) => self.import_outline_file(&name, &bytes),
             }
-        });
+        }
+    }
+
+    /// Replaces the outline with one read from a file, in the format given by its extension.
+    fn import_outline_file(&mut self, name: &str, bytes: &[u8]) {
+        let Some(format) = name
+            .rsplit_once('.')
+            .and_then(|(_, extension)| extension.parse::<OutlineFormat>().ok())
+        else {
+            self.report_error(format!("Failed to import {}: unknown outline format", name));
+            return;
+        };
+        let page_count = self
+            .doc
+            .as_ref()
+            .map_or(0, |doc| doc.get_pages().len() as u32);
+        let text = String::from_utf8_lossy(bytes);
+        let outline = import_outline(&text, format.refine_by_content(&text))
+            .and_then(|outline| check_page_numbers(&outline, page_count).map(|_| outline));
+        match outline {
+            Ok(outline) => {
+                //an imported empty outline removes the bookmarks when saved, like an emptied one
+                self.outline = Some(outline);
+                self.outline_editor.cancel_drag();
+                self.history.record(self.edit_state());
+            }
+            Err(e) => self.report_error(format!("Failed to import {}: {}", name, e)),
+        }
+    }
+
+    /// Replaces the current document, discarding fonts and outline generated for the previous one.
+    /// The outline the document already has is shown until a new one is generated.
+    fn load_pdf(&mut self, ctx: &egui::Context, file_name: String, bytes: &[u8]) {
+        let doc = match load_document(bytes) {
+            Ok(doc) => doc,
+            Err(e) => {
+                self.report_error(format!("Failed to open {}: {}", file_name, e));
+                return;
+            }
+        };
+        //start from the document's own bookmarks, if it has any
+        let outline = match doc.read_outline() {
+            Ok(outline) if !outline.is_empty() => Some(outline),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Failed to read the existing outline: {}", e);
+                None
+            }
+        };
+        let page_count = doc.get_pages().len() as u32;
+        *self = Self {
+            file_name,
+            outline,
+            outline_editor: OutlineEditor::new(page_count),
+            doc: Some(Arc::new(doc)),
+            errors: std::mem::take(&mut self.errors),
+            ..Default::default()
+        };
+        //edits made while the pages are read can be undone back to the document's own outline
+        self.history = History::new(self.edit_state());
+        self.start_font_scan(ctx);
+    }
+
+    fn output_file_name(&self) -> String {
+        self.output_file_name_with_extension("pdf")
+    }
+
+    fn output_file_name_with_extension(&self, extension: &str) -> String {
+        match self.file_name.rsplit_once('.') {
+            Some((stem, _)) => format!("{}_outline.{}", stem, extension),
+            None => format!("output.{}", extension),
+        }
+    }
+
+    fn export_outline(&mut self, format: OutlineFormat) {
+        let Some(outline) = &self.outline else {
+            return;
+        };
+        match export_outline(outline, format) {
+            Ok(text) => {
+                let file_name = self.output_file_name_with_extension(format.extension());
+                if let Err(e) = save_file_from_rust(text.into_bytes(), &file_name) {
+                    self.report_error(e);
+                }
+            }
+            Err(e) => self.report_error(format!("Failed to export the outline: {}", e)),
+        }
     }
 
     fn font_table(&mut self, ui: &mut egui::Ui) {
         if self.fonts.is_none() {
-            if let Some(doc) = &self.doc {
-                let fonts = doc.get_all_fonts().unwrap();
-                self.fonts = Some(
-                    fonts
-                        .iter()
-                        .map(|(k, &v)| FontRow {
-                            font: k.clone(),
-                            count: v,
-                            level: OutlineLevel::None,
-                        })
-                        .collect(),
-                );
+            //reading the fonts was cancelled or failed
+            if self.doc.is_some() && self.task.is_none() {
+                ui.heading("Fonts");
+                if ui.button("Read Fonts").clicked() {
+                    self.start_font_scan(ui.ctx());
+                }
             }
         } else {
             ui.heading("Fonts");
+            self.font_filter_bar(ui);
 
+            let levels_before = self.font_levels();
+            let mut level_count = self.level_count;
+            let mut suggest = false;
+            let mut sort = self.font_sort;
+            let order = self.shown_font_rows();
             TableBuilder::new(ui)
                 .resizable(true)
                 .striped(true)
                 .column(Column::initial(100.0))
                 .column(Column::initial(125.0))
-                .column(Column::auto().resizable(true))
-                .column(Column::exact(200.0))
+                .columns(Column::auto().resizable(true), 5)
+                .column(Column::remainder().at_least(200.0))
                 .header(20.0, |mut header| {
                     header.col(|ui| {
-                        ui.label("Name");
+                        Self::sort_header(ui, "Name", FontColumn::Name, &mut sort);
+                    });
+                    header.col(|ui| {
+                        Self::sort_header(ui, "Size", FontColumn::Size, &mut sort);
                     });
                     header.col(|ui| {
-                        ui.label("Size");
+                        Self::sort_header(ui, "Page Count", FontColumn::Pages, &mut sort);
                     });
                     header.col(|ui| {
-                        ui.label("Page Count");
+                        Self::sort_header(ui, "Runs", FontColumn::Runs, &mut sort)
+                            .on_hover_text("Separate pieces of text shown in the font");
+                    });
+                    header.col(|ui| {
+                        Self::sort_header(ui, "Glyphs", FontColumn::Glyphs, &mut sort)
+                            .on_hover_text("Characters shown in the font, not counting spaces");
+                    });
+                    header.col(|ui| {
+                        Self::sort_header(ui, "Pages", FontColumn::FirstPage, &mut sort)
+                            .on_hover_text("First and last page the font is used on");
+                    });
+                    header.col(|ui| {
+                        Self::sort_header(ui, "Heading", FontColumn::Heading, &mut sort)
+                            .on_hover_text("How likely the font is to be used for headings");
+                        if ui
+                            .small_button("Suggest")
+                            .on_hover_text("Assign the suggested levels to every font")
+                            .clicked()
+                        {
+                            suggest = true;
+                        }
                     });
                     header.col(|ui| {
                         ui.label("Outline Level");
+                        if ui.small_button("+").on_hover_text("Add a level").clicked() {
+                            level_count += 1;
+                        }
+                        ui.add_enabled_ui(level_count > 1, |ui| {
+                            if ui
+                                .small_button("−")
+                                .on_hover_text("Remove the last level")
+                                .clicked()
+                            {
+                                level_count -= 1;
+                            }
+                        });
                     });
                 })
                 .body(|body| {
                     if let Some(fonts) = &mut self.fonts {
-                        body.rows(20.0, fonts.len(), |index, mut row| {
+                        body.rows(20.0, order.len(), |row_index, mut row| {
+                            let index = order[row_index];
                             row.col(|ui| {
-                                ui.add(
+                                let label = ui.add(
                                     egui::Label::new(&fonts[index].font.base_font).truncate(true),
                                 );
+                                Self::font_samples_tooltip(label, &fonts[index].stats);
                             });
                             row.col(|ui| {
                                 let size = fonts[index].font.size;
@@ -188,27 +558,382 @@ impl App {
                                 ui.add(egui::Label::new(size_str).truncate(true));
                             });
                             row.col(|ui| {
-                                ui.label(format!("{}", fonts[index].count));
+                                ui.label(format!("{}", fonts[index].stats.page_count));
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{}", fonts[index].stats.runs));
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{}", fonts[index].stats.glyphs));
+                            });
+                            row.col(|ui| {
+                                let stats = &fonts[index].stats;
+                                if stats.first_page == stats.last_page {
+                                    ui.label(format!("{}", stats.first_page));
+                                } else {
+                                    ui.label(format!("{}–{}", stats.first_page, stats.last_page));
+                                }
+                            });
+                            row.col(|ui| {
+                                let row = &fonts[index];
+                                let label = ui.label(format!("{:.0}%", row.confidence * 100.0));
+                                if let OutlineLevel::Heading(depth) = row.suggested_level {
+                                    label.on_hover_text(format!(
+                                        "Suggested as a level {} heading",
+                                        depth + 1
+                                    ));
+                                }
                             });
                             row.col(|ui| {
                                 let level = &mut fonts[index].level;
-                                Self::outline_level_buttons(ui, level);
+                                Self::outline_level_buttons(ui, level, level_count);
                             });
                         });
                     }
                 });
+            self.font_sort = sort;
+            if suggest {
+                for font in self.fonts.iter_mut().flatten() {
+                    font.level = font.suggested_level;
+                }
+            }
+            let level_count_before = self.level_count;
+            self.set_level_count(level_count);
             self.update_heading_fonts();
+            if level_count != level_count_before || self.font_levels() != levels_before {
+                self.history.record(self.edit_state());
+            }
+        }
+    }
+
+    fn start_font_scan(&mut self, ctx: &egui::Context) {
+        if let Some(doc) = &self.doc {
+            let job = FontScanJob::new(Arc::clone(doc), self.level_count);
+            let task = Task::spawn(ctx, "Reading pages…", job.page_count(), job);
+            self.task = Some(AppTask::FontScan(task));
+        }
+    }
+
+    fn start_outline_generation(&mut self, ctx: &egui::Context) {
+        if let Some(text_index) = &self.text_index {
+            let job = GenerateOutlineJob::new(Arc::clone(text_index), self.heading_fonts.to_vec());
+            let task = Task::spawn(ctx, "Finding headings…", job.page_count(), job);
+            self.task = Some(AppTask::GenerateOutline(task));
+        }
+    }
+
+    fn is_generating_outline(&self) -> bool {
+        matches!(self.task, Some(AppTask::GenerateOutline(_)))
+    }
+
+    fn start_save(&mut self, ctx: &egui::Context) {
+        if let (Some(doc), Some(outline)) = (&self.doc, &self.outline) {
+            let job = SaveJob::new(Arc::clone(doc), outline.clone(), self.insert_mode);
+            let task = Task::spawn(ctx, "Saving…", job.step_count(), job);
+            self.task = Some(AppTask::Save(task));
+        }
+    }
+
+    /// Fills the font table, with the suggested heading fonts assigned their levels.
+    fn apply_font_scan(&mut self, scan: FontScan) {
+        let FontScan {
+            text_index,
+            fonts,
+            suggestions,
+        } = scan;
+        self.fonts = Some(
+            fonts
+                .into_iter()
+                .map(|(font, stats)| {
+                    let suggestion = suggestions.get(&font);
+                    let suggested_level = match suggestion.and_then(|s| s.level) {
+                        Some(depth) => OutlineLevel::Heading(depth),
+                        None => OutlineLevel::None,
+                    };
+                    FontRow {
+                        font,
+                        stats,
+                        //suggestions are assigned up front, for the user to override
+                        level: suggested_level,
+                        suggested_level,
+                        confidence: suggestion.map_or(0.0, |s| s.confidence),
+                    }
+                })
+                .collect(),
+        );
+        //the text that could be read is still worth listing, but the user should know about the rest
+        let problems: Vec<_> = text_index.problems().collect();
+        if let Some(first) = problems.first() {
+            let message = match problems.len() {
+                1 => format!("Some text was skipped: {}", first),
+                count => format!(
+                    "Some text was skipped: {} (and {} more problems)",
+                    first,
+                    count - 1
+                ),
+            };
+            self.report_error(message);
+        }
+        self.text_index = Some(Arc::new(text_index));
+        self.update_heading_fonts();
+        //the suggested levels can be undone like any other edit
+        self.history.record(self.edit_state());
+    }
+
+    /// Takes the output of the background task once it is done.
+    fn poll_task(&mut self, ctx: &egui::Context) {
+        match &mut self.task {
+            Some(AppTask::FontScan(task)) => {
+                if let Some(result) = task.poll(ctx) {
+                    self.task = None;
+                    match result {
+                        Ok(Ok(scan)) => self.apply_font_scan(scan),
+                        Ok(Err(e)) => self.report_error(format!("Failed to read the fonts: {}", e)),
+                        Err(e) => self.report_error(format!("Failed to read the fonts: {}", e)),
+                    }
+                }
+            }
+            Some(AppTask::GenerateOutline(task)) => {
+                if let Some(result) = task.poll(ctx) {
+                    self.task = None;
+                    match result {
+                        Ok(outline) => {
+                            self.outline = Some(outline);
+                            self.outline_editor.cancel_drag();
+                            self.history.record(self.edit_state());
+                        }
+                        Err(e) => {
+                            self.report_error(format!("Failed to generate the outline: {}", e))
+                        }
+                    }
+                }
+            }
+            Some(AppTask::Save(task)) => {
+                if let Some(result) = task.poll(ctx) {
+                    self.task = None;
+                    match result {
+                        Ok(Ok(data)) => {
+                            if let Err(e) = save_file_from_rust(data, &self.output_file_name()) {
+                                self.report_error(e);
+                            }
+                        }
+                        Ok(Err(e)) => self.report_error(format!("Failed to save the PDF: {}", e)),
+                        Err(e) => self.report_error(format!("Failed to save the PDF: {}", e)),
+                    }
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// Shows a failure to the user until they dismiss it.
+    fn report_error(&mut self, message: String) {
+        log::error!("{}", message);
+        self.errors.push(message);
+    }
+
+    /// Lists the errors that weren't dismissed yet, each with a button to dismiss it.
+    fn error_panel(&mut self, ctx: &egui::Context) {
+        if self.errors.is_empty() {
+            return;
+        }
+        egui::TopBottomPanel::top("Errors").show(ctx, |ui| {
+            let mut dismissed = None;
+            for (index, message) in self.errors.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        dismissed = Some(index);
+                    }
+                    ui.colored_label(ui.visuals().error_fg_color, message);
+                });
+            }
+            if let Some(index) = dismissed {
+                self.errors.remove(index);
+            }
+            if self.errors.len() > 1 && ui.button("Dismiss All").clicked() {
+                self.errors.clear();
+            }
+        });
+    }
+
+    /// Shows the progress of the background task, with a button to cancel it.
+    fn task_progress(&mut self, ui: &mut egui::Ui) {
+        let Some(task) = &self.task else {
+            return;
+        };
+        let (description, progress) = task.status();
+        ui.add(
+            egui::ProgressBar::new(progress.fraction())
+                .desired_width(250.0)
+                .animate(true)
+                .text(format!(
+                    "{} {}/{}",
+                    description,
+                    progress.done(),
+                    progress.total()
+                )),
+        );
+        if ui.button("Cancel").clicked() {
+            //dropping the task stops it
+            self.task = None;
+        }
+    }
+
+    fn font_filter_bar(&mut self, ui: &mut egui::Ui) {
+        let font_count = self.fonts.as_ref().map_or(0, Vec::len);
+        let shown = self.shown_font_rows().len();
+        let filter = &mut self.font_filter;
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut filter.name)
+                    .hint_text("Filter by name")
+                    .desired_width(150.0),
+            );
+            ui.label("Min size");
+            ui.add(
+                egui::DragValue::new(&mut filter.min_size)
+                    .clamp_range(0.0..=f32::MAX)
+                    .speed(0.5),
+            );
+            if filter.is_active() {
+                if ui.small_button("Clear").clicked() {
+                    *filter = FontFilter::default();
+                }
+                ui.weak(format!("{} of {} fonts", shown, font_count));
+            }
+        });
+    }
+
+    /// Indices of the font table rows that pass the filter, in the order they are shown in.
+    fn shown_font_rows(&self) -> Vec<usize> {
+        let Some(fonts) = &self.fonts else {
+            return vec![];
+        };
+        //filtered out rows keep their levels, they are only hidden
+        let mut order: Vec<usize> = (0..fonts.len())
+            .filter(|&index| self.font_filter.matches(&fonts[index].font))
+            .collect();
+        let sort = self.font_sort;
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&fonts[a], &fonts[b]);
+            let ordering = match sort.column {
+                FontColumn::Name => a.font.base_font.cmp(&b.font.base_font),
+                //`PdfFont` orders by descending size
+                FontColumn::Size => b.font.cmp(&a.font),
+                FontColumn::Pages => a.stats.page_count.cmp(&b.stats.page_count),
+                FontColumn::Runs => a.stats.runs.cmp(&b.stats.runs),
+                FontColumn::Glyphs => a.stats.glyphs.cmp(&b.stats.glyphs),
+                FontColumn::FirstPage => (a.stats.first_page, a.stats.last_page)
+                    .cmp(&(b.stats.first_page, b.stats.last_page)),
+                FontColumn::Heading => a.confidence.total_cmp(&b.confidence),
+            };
+            if sort.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        order
+    }
+
+    /// A column header that sorts the table by the column when clicked,
+    /// or reverses the order if it is already sorted by it.
+    fn sort_header(
+        ui: &mut egui::Ui,
+        label: &str,
+        column: FontColumn,
+        sort: &mut FontSort,
+    ) -> egui::Response {
+        let selected = sort.column == column;
+        let text = match (selected, sort.descending) {
+            (true, true) => format!("{} ⬇", label),
+            (true, false) => format!("{} ⬆", label),
+            (false, _) => label.to_string(),
+        };
+        let response = ui.selectable_label(selected, text);
+        if response.clicked() {
+            if selected {
+                sort.descending = !sort.descending;
+            } else {
+                *sort = FontSort {
+                    column,
+                    //counts and scores are most useful largest first
+                    descending: !matches!(column, FontColumn::Name | FontColumn::FirstPage),
+                };
+            }
+        }
+        response
+    }
+
+    /// Shows the text samples of a font when hovering over its name.
+    fn font_samples_tooltip(label: egui::Response, stats: &FontStats) {
+        if stats.samples.is_empty() {
+            return;
+        }
+        label.on_hover_ui(|ui| {
+            for sample in &stats.samples {
+                ui.horizontal(|ui| {
+                    ui.weak(format!("p. {}", sample.page_number));
+                    ui.label(&sample.text);
+                });
+            }
+        });
+    }
+
+    fn font_levels(&self) -> Vec<OutlineLevel> {
+        self.fonts.iter().flatten().map(|font| font.level).collect()
+    }
+
+    fn edit_state(&self) -> EditState {
+        EditState {
+            level_count: self.level_count,
+            levels: self.font_levels(),
+            outline: self.outline.clone(),
+        }
+    }
+
+    fn restore(&mut self, state: EditState) {
+        self.level_count = state.level_count;
+        if let Some(fonts) = &mut self.fonts {
+            for (font, level) in fonts.iter_mut().zip(state.levels) {
+                font.level = level;
+            }
+        }
+        self.update_heading_fonts();
+        self.outline = state.outline;
+        self.outline_editor.cancel_drag();
+    }
+
+    fn undo(&mut self) {
+        if let Some(state) = self.history.undo() {
+            self.restore(state);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.history.redo() {
+            self.restore(state);
+        }
+    }
+
+    /// Changes the number of outline levels, unassigning fonts from removed levels.
+    fn set_level_count(&mut self, level_count: usize) {
+        self.level_count = level_count;
+        if let Some(fonts) = &mut self.fonts {
+            for font in fonts {
+                if matches!(font.level, OutlineLevel::Heading(depth) if depth >= level_count) {
+                    font.level = OutlineLevel::None;
+                }
+            }
         }
     }
 
     fn update_heading_fonts(&mut self) {
-        self.heading_fonts = [vec![], vec![], vec![]];
+        self.heading_fonts = vec![vec![]; self.level_count];
         if let Some(fonts) = &self.fonts {
             for font in fonts {
                 let index = match font.level {
-                    OutlineLevel::First => 0,
-                    OutlineLevel::Second => 1,
-                    OutlineLevel::Third => 2,
+                    OutlineLevel::Heading(depth) => depth,
                     OutlineLevel::None => continue,
                 };
                 self.heading_fonts[index].push(font.font.clone());
@@ -216,16 +941,14 @@ impl App {
         }
     }
 
-    fn outline_level_buttons(ui: &mut egui::Ui, level: &mut OutlineLevel) {
+    fn outline_level_buttons(ui: &mut egui::Ui, level: &mut OutlineLevel, level_count: usize) {
         ui.horizontal(|ui| {
-            for variant in [
-                OutlineLevel::First,
-                OutlineLevel::Second,
-                OutlineLevel::Third,
-            ] {
+            for depth in 0..level_count {
+                let variant = OutlineLevel::Heading(depth);
                 let selected = *level == variant;
                 if ui
-                    .add(egui::RadioButton::new(selected, format!("{:?}", variant)))
+                    .add(egui::RadioButton::new(selected, format!("{}", depth + 1)))
+                    .on_hover_text(format!("Level {} heading", depth + 1))
                     .clicked()
                 {
                     *level = if selected {
@@ -237,18 +960,4 @@ impl App {
             }
         });
     }
-
-    fn outline_preview(ui: &mut egui::Ui, outline: &PdfOutline, mut id: usize) {
-        for entry in outline {
-            ui.push_id(id, |ui| {
-                egui::CollapsingHeader::new(entry.title.clone())
-                    .default_open(true)
-                    .show(ui, |ui| {
-                        id += 1;
-                        Self::outline_preview(ui, &entry.children, id);
-                    });
-            });
-            id += entry.children.len() + 1;
-        }
-    }
 }
@@ -0,0 +1,261 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// Work done a part at a time, so that it can be cancelled and its progress shown.
+pub trait Job: Send + 'static {
+    type Output: Send + 'static;
+
+    /// Does the next part of the work and reports it to `progress`.
+    /// Returns the output once all of the work is done.
+    fn step(&mut self, progress: &Progress) -> Option<Self::Output>;
+}
+
+/// How much of a job is done, shared between the job and the UI.
+#[derive(Debug)]
+pub struct Progress {
+    done: AtomicUsize,
+    total: usize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            done: AtomicUsize::new(0),
+            total,
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn advance(&self, count: usize) {
+        self.done.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed).min(self.total)
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.done() as f32 / self.total.max(1) as f32
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The job of a task stopped without finishing, because it panicked.
+#[derive(Debug, PartialEq)]
+pub struct TaskFailed;
+
+impl std::fmt::Display for TaskFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the background task stopped unexpectedly")
+    }
+}
+
+impl std::error::Error for TaskFailed {}
+
+/// A job run on a worker thread natively. Browsers have no threads to spare,
+/// so there it is run in parts between frames instead, keeping the page responsive.
+pub struct Task<T> {
+    description: String,
+    progress: Arc<Progress>,
+    #[cfg(not(target_arch = "wasm32"))]
+    receiver: std::sync::mpsc::Receiver<T>,
+    #[cfg(target_arch = "wasm32")]
+    job: Box<dyn Job<Output = T>>,
+}
+
+impl<T> std::fmt::Debug for Task<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Task")
+            .field("description", &self.description)
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Starts a job that reports `total` units of work to its progress.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn(
+        ctx: &egui::Context,
+        description: impl Into<String>,
+        total: usize,
+        mut job: impl Job<Output = T>,
+    ) -> Self {
+        let progress = Arc::new(Progress::new(total));
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let thread_progress = Arc::clone(&progress);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            while !thread_progress.is_cancelled() {
+                let output = job.step(&thread_progress);
+                ctx.request_repaint();
+                if let Some(output) = output {
+                    //the task was dropped if sending fails, nobody is waiting for the output
+                    let _ = sender.send(output);
+                    break;
+                }
+            }
+        });
+        Self {
+            description: description.into(),
+            progress,
+            receiver,
+        }
+    }
+
+    /// Starts a job that reports `total` units of work to its progress.
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn(
+        _ctx: &egui::Context,
+        description: impl Into<String>,
+        total: usize,
+        job: impl Job<Output = T>,
+    ) -> Self {
+        Self {
+            description: description.into(),
+            progress: Arc::new(Progress::new(total)),
+            job: Box::new(job),
+        }
+    }
+
+    /// Returns the output of the job once it is done, or an error if its thread panicked.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll(&mut self, _ctx: &egui::Context) -> Option<Result<T, TaskFailed>> {
+        match self.receiver.try_recv() {
+            Ok(output) => Some(Ok(output)),
+            Err(std::sync::mpsc::TryRecvError::Empty) => None,
+            //the thread only ends without sending the output if it was cancelled or panicked
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Some(Err(TaskFailed)),
+        }
+    }
+
+    /// Runs the job for part of a frame, and returns its output once it is done.
+    /// A panic aborts the whole app in the browser, so the job never fails here.
+    #[cfg(target_arch = "wasm32")]
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<Result<T, TaskFailed>> {
+        let start = performance_now();
+        while performance_now() - start < FRAME_BUDGET_MS {
+            if let Some(output) = self.job.step(&self.progress) {
+                return Some(Ok(output));
+            }
+        }
+        ctx.request_repaint();
+        None
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+}
+
+/// Dropping a task cancels it, the job stops after the part it is working on.
+impl<T> Drop for Task<T> {
+    fn drop(&mut self) {
+        self.progress.cancel();
+    }
+}
+
+/// Time spent on a job each frame in the browser, in milliseconds
+#[cfg(target_arch = "wasm32")]
+const FRAME_BUDGET_MS: f64 = 12.0;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// Counts to `steps`, or panics on the step after `panic_after`.
+    struct Counter {
+        count: usize,
+        steps: usize,
+        panic_after: Option<usize>,
+    }
+
+    impl Job for Counter {
+        type Output = usize;
+
+        fn step(&mut self, progress: &Progress) -> Option<Self::Output> {
+            if Some(self.count) == self.panic_after {
+                panic!("the job failed");
+            }
+            self.count += 1;
+            progress.advance(1);
+            (self.count == self.steps).then_some(self.count)
+        }
+    }
+
+    fn counter(steps: usize, panic_after: Option<usize>) -> Task<usize> {
+        let job = Counter {
+            count: 0,
+            steps,
+            panic_after,
+        };
+        Task::spawn(&egui::Context::default(), "Counting", steps, job)
+    }
+
+    fn wait_for<T: Send + 'static>(task: &mut Task<T>) -> Result<T, TaskFailed> {
+        let ctx = egui::Context::default();
+        let start = Instant::now();
+        loop {
+            if let Some(result) = task.poll(&ctx) {
+                return result;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "the task hangs");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn finished_tasks_return_their_output() {
+        let mut task = counter(5, None);
+        assert_eq!(wait_for(&mut task), Ok(5));
+        assert_eq!(task.progress().done(), 5);
+        assert_eq!(task.progress().fraction(), 1.0);
+        assert_eq!(task.description(), "Counting");
+    }
+
+    #[test]
+    fn panicking_tasks_fail() {
+        let mut task = counter(5, Some(2));
+        assert_eq!(wait_for(&mut task), Err(TaskFailed));
+        assert_eq!(task.progress().done(), 2);
+    }
+
+    #[test]
+    fn progress_is_capped_at_the_total() {
+        let progress = Progress::new(2);
+        progress.advance(3);
+        assert_eq!(progress.done(), 2);
+        assert_eq!(Progress::new(0).fraction(), 0.0);
+    }
+}
@@ -0,0 +1,460 @@
+#![warn(clippy::all, rust_2018_idioms)]
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use automatic_pdf_outline_generator::pdf_tools::{
+    load_document,
+    pdf_font::PdfFont,
+    pdf_outline::{
+        check_page_numbers, export_outline, import_outline, print_outline, OutlineFormat,
+    },
+    InsertMode, PdfFontReader, PdfHeadingSuggester, PdfOutlineGenerator, PdfOutlineInserter,
+    PdfTextIndex,
+};
+use lopdf::Document;
+
+const USAGE: &str = "\
+Usage:
+    pdf-outline <INPUT> --list-fonts
+    pdf-outline <INPUT> --level <FONTS> [--level <FONTS>...] [-o <OUTPUT>] [--mode <MODE>]
+                [--print] [--export <PATH> [--export-format <FORMAT>]]
+    pdf-outline <INPUT> --import <PATH> [-o <OUTPUT>] [--mode <MODE>]
+                [--print] [--export <PATH> [--export-format <FORMAT>]]
+    pdf-outline <INPUT> --suggest <LEVELS> [-o <OUTPUT>] [--mode <MODE>]
+                [--print] [--export <PATH> [--export-format <FORMAT>]]
+
+Options:
+    --list-fonts         Print every font used in INPUT with its page count, how likely
+                         it is to be a heading font and the level suggested for it
+    --level <FONTS>      Heading fonts for the next outline level, separated by ';'
+                         Each font is written as BaseFont:size or BaseFont:width,height
+    --import <PATH>      Use the outline in PATH instead of generating one; text, JSON or
+                         YAML, chosen by its extension. Text files have one entry per line
+                         as title<TAB>page, indented to nest entries. pdftk dump_data and
+                         qpdf --json output are recognised by their contents
+    --suggest <LEVELS>   Use the fonts most likely to be heading fonts for up to LEVELS
+                         outline levels instead of giving them with --level
+    -o, --output <PATH>  Write INPUT with the generated outline to PATH
+    --mode <MODE>        What to do with an existing outline: replace (default),
+                         append the generated one after it, or merge both by page
+    --print              Print the generated outline to stdout
+    --export <PATH>      Write the generated outline to PATH as text, JSON or YAML,
+                         chosen by its extension (.txt, .json, .yaml)
+    --export-format <FORMAT>
+                         Export as text, json, yaml, pdftk (dump_data bookmarks) or qpdf
+                         (--json outlines) regardless of the extension
+    -h, --help           Print this message
+
+Exit codes:
+    0  success
+    1  the PDF could not be read, processed or saved, or no headings were found
+    2  invalid command-line arguments";
+
+/// Number of levels --list-fonts suggests heading fonts for without --suggest
+const DEFAULT_SUGGESTED_LEVELS: usize = 3;
+
+#[derive(Debug, Default)]
+struct Args {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    export: Option<(PathBuf, OutlineFormat)>,
+    import: Option<PathBuf>,
+    /// Number of outline levels to suggest heading fonts for
+    suggest: Option<usize>,
+    heading_fonts: Vec<Vec<PdfFont>>,
+    mode: InsertMode,
+    list_fonts: bool,
+    print: bool,
+}
+
+#[derive(Debug)]
+enum Failure {
+    Usage(String),
+    Processing(String),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> ExitCode {
+    //warnings are how the user learns that some text was skipped
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let result = parse_args(std::env::args().skip(1)).and_then(|args| match args {
+        Some(args) => run(&args),
+        None => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Failure::Processing(message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// The command-line tool has no use in the browser.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+/// Returns `None` when help was requested.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, Failure> {
+    let mut parsed = Args::default();
+    let mut input = None;
+    let mut export = None;
+    let mut export_format = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| Failure::Usage(format!("{} requires a value", flag)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--list-fonts" => parsed.list_fonts = true,
+            "--print" => parsed.print = true,
+            "-o" | "--output" => parsed.output = Some(value(&arg)?.into()),
+            "--export" => export = Some(PathBuf::from(value(&arg)?)),
+            "--export-format" => {
+                export_format = Some(value(&arg)?.parse().map_err(Failure::Usage)?)
+            }
+            "--import" => parsed.import = Some(PathBuf::from(value(&arg)?)),
+            "--suggest" => {
+                let levels = value(&arg)?;
+                match levels.parse() {
+                    Ok(levels) if levels > 0 => parsed.suggest = Some(levels),
+                    _ => {
+                        return Err(Failure::Usage(format!(
+                            "--suggest requires a number of levels, got '{}'",
+                            levels
+                        )))
+                    }
+                }
+            }
+            "--mode" => parsed.mode = value(&arg)?.parse().map_err(Failure::Usage)?,
+            "--level" => {
+                let fonts = value(&arg)?
+                    .split(';')
+                    .filter(|font| !font.trim().is_empty())
+                    .map(|font| font.trim().parse().map_err(Failure::Usage))
+                    .collect::<Result<Vec<PdfFont>, _>>()?;
+                if fonts.is_empty() {
+                    return Err(Failure::Usage(String::from("--level requires a font")));
+                }
+                parsed.heading_fonts.push(fonts);
+            }
+            x if x.starts_with('-') => {
+                return Err(Failure::Usage(format!("unknown option '{}'", x)))
+            }
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(Failure::Usage(format!("unexpected argument '{}'", arg))),
+        }
+    }
+
+    parsed.input = input.ok_or_else(|| Failure::Usage(String::from("missing INPUT")))?;
+    if let Some(import) = &parsed.import {
+        format_of("--import", import)?;
+    }
+    parsed.export = match (export, export_format) {
+        (Some(path), Some(format)) => Some((path, format)),
+        (Some(path), None) => {
+            let format = format_of("--export", &path)?;
+            Some((path, format))
+        }
+        (None, Some(_)) => {
+            return Err(Failure::Usage(String::from(
+                "--export-format requires --export",
+            )))
+        }
+        (None, None) => None,
+    };
+    if !parsed.list_fonts {
+        let sources = [
+            !parsed.heading_fonts.is_empty(),
+            parsed.import.is_some(),
+            parsed.suggest.is_some(),
+        ];
+        match sources.iter().filter(|&&given| given).count() {
+            0 => {
+                return Err(Failure::Usage(String::from(
+                    "at least one --level, an --import or --suggest is required unless --list-fonts is given",
+                )))
+            }
+            1 => (),
+            _ => {
+                return Err(Failure::Usage(String::from(
+                    "--level, --import and --suggest cannot be used together",
+                )))
+            }
+        }
+        if parsed.output.is_none() && !parsed.print && parsed.export.is_none() {
+            return Err(Failure::Usage(String::from(
+                "nothing to do, pass --output, --print and/or --export",
+            )));
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// Returns the outline format the extension of the path given to `flag` stands for.
+fn format_of(flag: &str, path: &Path) -> Result<OutlineFormat, Failure> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .ok_or_else(|| Failure::Usage(format!("{} requires a file extension", flag)))?
+        .parse()
+        .map_err(Failure::Usage)
+}
+
+fn read_text(doc: &Document) -> PdfTextIndex {
+    let text_index = PdfTextIndex::new(doc);
+    for problem in text_index.problems() {
+        log::warn!("{}", problem);
+    }
+    text_index
+}
+
+/// Groups the suggested heading fonts by their level.
+fn suggested_heading_fonts(
+    text_index: &PdfTextIndex,
+    levels: usize,
+) -> Result<Vec<Vec<PdfFont>>, Failure> {
+    let suggestions = text_index
+        .suggest_heading_fonts(levels)
+        .map_err(|e| Failure::Processing(format!("failed to suggest heading fonts: {}", e)))?;
+    let mut heading_fonts = vec![vec![]; levels];
+    for (font, suggestion) in suggestions {
+        if let Some(depth) = suggestion.level {
+            heading_fonts[depth].push(font);
+        }
+    }
+    //levels without fonts would leave gaps in the outline
+    heading_fonts.retain(|fonts| !fonts.is_empty());
+    Ok(heading_fonts)
+}
+
+fn run(args: &Args) -> Result<(), Failure> {
+    let bytes = std::fs::read(&args.input).map_err(|e| {
+        Failure::Processing(format!("failed to read {}: {}", args.input.display(), e))
+    })?;
+    let mut doc = load_document(&bytes).map_err(|e| {
+        Failure::Processing(format!("failed to load {}: {}", args.input.display(), e))
+    })?;
+
+    if args.list_fonts {
+        let text_index = read_text(&doc);
+        let fonts = text_index
+            .get_all_fonts()
+            .map_err(|e| Failure::Processing(format!("failed to read fonts: {}", e)))?;
+        let suggestions = text_index
+            .suggest_heading_fonts(args.suggest.unwrap_or(DEFAULT_SUGGESTED_LEVELS))
+            .map_err(|e| Failure::Processing(format!("failed to suggest heading fonts: {}", e)))?;
+        // Printed in the same form that --level accepts
+        for (font, stats) in fonts {
+            let suggestion = suggestions.get(&font);
+            let confidence = suggestion.map_or(0.0, |s| s.confidence);
+            let level = match suggestion.and_then(|s| s.level) {
+                Some(depth) => format!("\tlevel {}", depth + 1),
+                None => String::new(),
+            };
+            if font.size.0 == font.size.1 {
+                print!("{}:{}", font.base_font, font.size.0);
+            } else {
+                print!("{}:{},{}", font.base_font, font.size.0, font.size.1);
+            }
+            println!(
+                "\t{}\t{:.0}%{}",
+                stats.page_count,
+                confidence * 100.0,
+                level
+            );
+        }
+        return Ok(());
+    }
+
+    let outline = match &args.import {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| {
+                Failure::Processing(format!("failed to read {}: {}", path.display(), e))
+            })?;
+            let format = format_of("--import", path)?.refine_by_content(&text);
+            let outline = import_outline(&text, format).map_err(|e| {
+                Failure::Processing(format!("failed to import {}: {}", path.display(), e))
+            })?;
+            check_page_numbers(&outline, doc.get_pages().len() as u32).map_err(|e| {
+                Failure::Processing(format!("failed to import {}: {}", path.display(), e))
+            })?;
+            outline
+        }
+        None => {
+            let text_index = read_text(&doc);
+            let heading_fonts = match args.suggest {
+                Some(levels) => suggested_heading_fonts(&text_index, levels)?,
+                None => args.heading_fonts.clone(),
+            };
+            let outline = text_index
+                .generate_outline(&heading_fonts)
+                .map_err(|e| Failure::Processing(format!("failed to generate outline: {}", e)))?;
+            //inserting an empty outline would only remove the bookmarks the document has
+            if outline.is_empty() {
+                return Err(Failure::Processing(String::from(
+                    "none of the heading fonts were found in the document",
+                )));
+            }
+            outline
+        }
+    };
+    if args.print {
+        print_outline(&outline);
+    }
+    if let Some((path, format)) = &args.export {
+        let text = export_outline(&outline, *format)
+            .map_err(|e| Failure::Processing(format!("failed to export outline: {}", e)))?;
+        std::fs::write(path, text).map_err(|e| {
+            Failure::Processing(format!("failed to write {}: {}", path.display(), e))
+        })?;
+    }
+
+    if let Some(output) = &args.output {
+        doc.insert_outline(&outline, args.mode)
+            .map_err(|e| Failure::Processing(format!("failed to insert outline: {}", e)))?;
+        doc.save(output).map_err(|e| {
+            Failure::Processing(format!("failed to save {}: {}", output.display(), e))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, Failure> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn usage_error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(Failure::Usage(message)) => message,
+            Err(Failure::Processing(message)) => panic!("{:?}: {}", args, message),
+            Ok(_) => panic!("{:?} was accepted", args),
+        }
+    }
+
+    #[test]
+    fn levels_are_lists_of_fonts() {
+        let args = parse(&[
+            "in.pdf",
+            "--level",
+            "Helvetica-Bold:18",
+            "--level",
+            "Helvetica-Bold:14; Helvetica-BoldOblique:14,12;",
+            "-o",
+            "out.pdf",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.input, PathBuf::from("in.pdf"));
+        assert_eq!(args.output, Some(PathBuf::from("out.pdf")));
+        let font = |name: &str, size| PdfFont::new(String::from(name), size);
+        assert_eq!(
+            args.heading_fonts,
+            [
+                vec![font("Helvetica-Bold", (18.0, 18.0))],
+                vec![
+                    font("Helvetica-Bold", (14.0, 14.0)),
+                    font("Helvetica-BoldOblique", (14.0, 12.0)),
+                ],
+            ]
+        );
+        assert_eq!(args.mode, InsertMode::Replace);
+    }
+
+    #[test]
+    fn export_format_defaults_to_the_extension() {
+        let args = parse(&["in.pdf", "--suggest", "2", "--export", "outline.yaml"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.suggest, Some(2));
+        assert_eq!(
+            args.export,
+            Some((PathBuf::from("outline.yaml"), OutlineFormat::Yaml))
+        );
+
+        let args = parse(&[
+            "in.pdf",
+            "--import",
+            "toc.txt",
+            "--mode",
+            "merge",
+            "--export",
+            "bookmarks.txt",
+            "--export-format",
+            "pdftk",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.import, Some(PathBuf::from("toc.txt")));
+        assert_eq!(args.mode, InsertMode::Merge);
+        assert_eq!(
+            args.export,
+            Some((PathBuf::from("bookmarks.txt"), OutlineFormat::Pdftk))
+        );
+    }
+
+    #[test]
+    fn help_is_not_an_error() {
+        assert!(matches!(parse(&["--help"]), Ok(None)));
+        assert!(matches!(parse(&["in.pdf", "-h"]), Ok(None)));
+        assert!(parse(&["in.pdf", "--list-fonts"]).unwrap().is_some());
+    }
+
+    #[test]
+    fn invalid_arguments_are_usage_errors() {
+        assert_eq!(usage_error(&["--list-fonts"]), "missing INPUT");
+        assert_eq!(
+            usage_error(&["in.pdf", "--level"]),
+            "--level requires a value"
+        );
+        assert_eq!(
+            usage_error(&["in.pdf", "--level", ";", "--print"]),
+            "--level requires a font"
+        );
+        assert_eq!(
+            usage_error(&["in.pdf", "--level", "Helvetica:0", "--print"]),
+            "invalid font size '0' in 'Helvetica:0'"
+        );
+        assert_eq!(
+            usage_error(&["in.pdf", "--frobnicate"]),
+            "unknown option '--frobnicate'"
+        );
+        assert_eq!(
+            usage_error(&["in.pdf", "other.pdf", "--list-fonts"]),
+            "unexpected argument 'other.pdf'"
+        );
+        assert!(usage_error(&["in.pdf", "--suggest", "0", "--print"]).starts_with("--suggest"));
+        assert!(usage_error(&["in.pdf", "--print"]).starts_with("at least one --level"));
+        assert!(
+            usage_error(&["in.pdf", "--suggest", "1", "--level", "A:1", "--print"])
+                .ends_with("cannot be used together")
+        );
+        assert!(usage_error(&["in.pdf", "--suggest", "1"]).starts_with("nothing to do"));
+        assert_eq!(
+            usage_error(&["in.pdf", "--list-fonts", "--export-format", "json"]),
+            "--export-format requires --export"
+        );
+        assert!(!usage_error(&["in.pdf", "--suggest", "1", "--export", "outline"]).is_empty());
+        assert!(
+            !usage_error(&["in.pdf", "--suggest", "1", "--mode", "prepend", "--print"]).is_empty()
+        );
+    }
+}
@@ -0,0 +1,122 @@
+/// Number of states kept; the oldest are dropped first.
+const MAX_STATES: usize = 100;
+
+/// Undo and redo history of snapshots of some state.
+///
+/// The last recorded snapshot is the current state, so undoing returns the one before it.
+#[derive(Debug)]
+pub struct History<T> {
+    states: Vec<T>,
+    current: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            states: vec![],
+            current: 0,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new(initial: T) -> Self {
+        Self {
+            states: vec![initial],
+            current: 0,
+        }
+    }
+
+    /// Records a new current state, discarding any undone states.
+    /// Does nothing if the state didn't change.
+    pub fn record(&mut self, state: T) {
+        if self.states.get(self.current) == Some(&state) {
+            return;
+        }
+        self.states.truncate(self.current + 1);
+        self.states.push(state);
+        if self.states.len() > MAX_STATES {
+            self.states.remove(0);
+        }
+        self.current = self.states.len() - 1;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.current + 1 < self.states.len()
+    }
+
+    /// Returns the state before the current one.
+    pub fn undo(&mut self) -> Option<T> {
+        if !self.can_undo() {
+            return None;
+        }
+        self.current -= 1;
+        self.states.get(self.current).cloned()
+    }
+
+    /// Returns the state that was undone last.
+    pub fn redo(&mut self) -> Option<T> {
+        if !self.can_redo() {
+            return None;
+        }
+        self.current += 1;
+        self.states.get(self.current).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_walk_the_states() {
+        let mut history = History::new(0);
+        history.record(1);
+        history.record(2);
+        assert_eq!(history.undo(), Some(1));
+        assert_eq!(history.undo(), Some(0));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(1));
+        assert_eq!(history.redo(), Some(2));
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn unchanged_state_is_not_recorded() {
+        let mut history = History::new(0);
+        history.record(0);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn recording_discards_undone_states() {
+        let mut history = History::new(0);
+        history.record(1);
+        history.record(2);
+        history.undo();
+        history.record(3);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(1));
+        assert_eq!(history.redo(), Some(3));
+    }
+
+    #[test]
+    fn oldest_states_are_dropped() {
+        let mut history = History::new(0);
+        for state in 1..=MAX_STATES + 10 {
+            history.record(state);
+        }
+        let mut undone = 0;
+        let mut oldest = MAX_STATES + 10;
+        while let Some(state) = history.undo() {
+            undone += 1;
+            oldest = state;
+        }
+        assert_eq!(undone, MAX_STATES - 1);
+        assert_eq!(oldest, 11);
+    }
+}
@@ -0,0 +1,319 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use lopdf::{Document, ObjectId};
+
+use crate::{
+    background::{Job, Progress},
+    pdf_tools::{
+        add_page_headings,
+        pdf_font::{FontStats, PdfFont},
+        pdf_outline::PdfOutline,
+        read_pages, FontDecoders, HeadingSuggestion, InsertMode, PageText, PdfError, PdfFontReader,
+        PdfHeadingSuggester, PdfOutlineInserter, PdfTextIndex,
+    },
+};
+
+/// What is known about the fonts of a document once all its pages are read.
+pub struct FontScan {
+    pub text_index: PdfTextIndex,
+    pub fonts: BTreeMap<PdfFont, FontStats>,
+    pub suggestions: BTreeMap<PdfFont, HeadingSuggestion>,
+}
+
+/// Reads the text of a document one page at a time.
+pub struct FontScanJob {
+    doc: Arc<Document>,
+    pages: Vec<(u32, ObjectId)>,
+    text: Vec<PageText>,
+    /// Kept between steps, so that each font is only decoded once
+    font_decoders: FontDecoders,
+    level_count: usize,
+}
+
+impl FontScanJob {
+    /// Suggests heading fonts for up to `level_count` levels once the pages are read.
+    pub fn new(doc: Arc<Document>, level_count: usize) -> Self {
+        let pages = doc.get_pages().into_iter().collect();
+        Self {
+            doc,
+            pages,
+            text: vec![],
+            font_decoders: FontDecoders::default(),
+            level_count,
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// Pages read at once, enough to keep every core busy with the `parallel` feature.
+fn pages_per_step() -> usize {
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    return rayon::current_num_threads() * 2;
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    return 1;
+}
+
+impl Job for FontScanJob {
+    type Output = Result<FontScan, PdfError>;
+
+    fn step(&mut self, progress: &Progress) -> Option<Self::Output> {
+        let remaining = &self.pages[self.text.len()..];
+        if !remaining.is_empty() {
+            let batch = &remaining[..remaining.len().min(pages_per_step())];
+            self.text
+                .extend(read_pages(&self.doc, batch, &self.font_decoders));
+            progress.advance(batch.len());
+            return None;
+        }
+
+        let text_index = PdfTextIndex {
+            pages: std::mem::take(&mut self.text),
+        };
+        let fonts = match text_index.get_all_fonts() {
+            Ok(fonts) => fonts,
+            Err(e) => return Some(Err(e)),
+        };
+        let suggestions = text_index
+            .suggest_heading_fonts(self.level_count)
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to suggest heading fonts: {}", e);
+                Default::default()
+            });
+        Some(Ok(FontScan {
+            text_index,
+            fonts,
+            suggestions,
+        }))
+    }
+}
+
+/// Finds the headings of one page at a time.
+pub struct GenerateOutlineJob {
+    text_index: Arc<PdfTextIndex>,
+    heading_fonts: Vec<Vec<PdfFont>>,
+    next_page: usize,
+    outline: PdfOutline,
+}
+
+impl GenerateOutlineJob {
+    pub fn new(text_index: Arc<PdfTextIndex>, heading_fonts: Vec<Vec<PdfFont>>) -> Self {
+        Self {
+            text_index,
+            heading_fonts,
+            next_page: 0,
+            outline: PdfOutline::new(),
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.text_index.pages.len()
+    }
+}
+
+impl Job for GenerateOutlineJob {
+    type Output = PdfOutline;
+
+    fn step(&mut self, progress: &Progress) -> Option<Self::Output> {
+        match self.text_index.pages.get(self.next_page) {
+            Some(page) => {
+                add_page_headings(&mut self.outline, page, &self.heading_fonts);
+                self.next_page += 1;
+                progress.advance(1);
+                None
+            }
+            None => Some(std::mem::take(&mut self.outline)),
+        }
+    }
+}
+
+/// Inserts an outline into a copy of a document and serializes it, copying, inserting
+/// and serializing in separate steps. Each of them is done in one go, so on the web
+/// the page still pauses while a large document is copied or written, but progress is
+/// shown and the save can be cancelled between them.
+pub struct SaveJob {
+    doc: Arc<Document>,
+    outline: PdfOutline,
+    mode: InsertMode,
+    /// The copy the outline is inserted into, once it is made
+    copy: Option<Document>,
+    inserted: bool,
+}
+
+impl SaveJob {
+    pub fn new(doc: Arc<Document>, outline: PdfOutline, mode: InsertMode) -> Self {
+        Self {
+            doc,
+            outline,
+            mode,
+            copy: None,
+            inserted: false,
+        }
+    }
+
+    pub fn step_count(&self) -> usize {
+        3
+    }
+}
+
+impl Job for SaveJob {
+    type Output = Result<Vec<u8>, PdfError>;
+
+    fn step(&mut self, progress: &Progress) -> Option<Self::Output> {
+        match &mut self.copy {
+            None => self.copy = Some((*self.doc).clone()),
+            Some(doc) if !self.inserted => {
+                if let Err(e) = doc.insert_outline(&self.outline, self.mode) {
+                    return Some(Err(e));
+                }
+                self.inserted = true;
+            }
+            Some(doc) => {
+                let mut data = vec![];
+                let result = doc.save_to(&mut data).map_err(PdfError::Save);
+                progress.advance(1);
+                return Some(result.map(|_| data));
+            }
+        }
+        progress.advance(1);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{
+        content::{Content, Operation},
+        dictionary, Object, Stream,
+    };
+
+    use super::*;
+    use crate::pdf_tools::{pdf_outline::PdfOutlineEntry, PdfOutlineReader};
+
+    /// Builds a document with a heading in Helvetica-Bold on each page.
+    fn document_with_headings(headings: &[&str]) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica-Bold",
+        });
+        let kids: Vec<Object> = headings
+            .iter()
+            .map(|heading| {
+                let content = Content {
+                    operations: vec![
+                        Operation::new("BT", vec![]),
+                        Operation::new("Tf", vec!["F1".into(), 18.into()]),
+                        Operation::new("Tj", vec![Object::string_literal(*heading)]),
+                        Operation::new("ET", vec![]),
+                    ],
+                };
+                let content_id =
+                    doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => dictionary! {
+                        "Font" => dictionary! { "F1" => font_id },
+                    },
+                })
+                .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    /// Runs a job to the end, and returns its output and the number of steps it took.
+    fn run<J: Job>(mut job: J, progress: &Progress) -> (J::Output, usize) {
+        for steps in 1..1000 {
+            if let Some(output) = job.step(progress) {
+                return (output, steps);
+            }
+        }
+        panic!("the job doesn't end");
+    }
+
+    fn heading_font() -> PdfFont {
+        PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0))
+    }
+
+    #[test]
+    fn font_scan_reads_every_page() {
+        let doc = Arc::new(document_with_headings(&["One", "Two", "Three"]));
+        let job = FontScanJob::new(doc, 2);
+        assert_eq!(job.page_count(), 3);
+        let progress = Progress::new(job.page_count());
+
+        let (scan, steps) = run(job, &progress);
+        let scan = scan.unwrap();
+        //the pages are read in batches, then the fonts counted in a last step
+        assert_eq!(steps, (3 + pages_per_step() - 1) / pages_per_step() + 1);
+        assert_eq!(progress.done(), 3);
+        assert_eq!(scan.text_index.pages.len(), 3);
+        assert_eq!(scan.fonts[&heading_font()].page_count, 3);
+        assert!(scan.suggestions.contains_key(&heading_font()));
+    }
+
+    #[test]
+    fn outline_generation_reads_a_page_per_step() {
+        let doc = document_with_headings(&["One", "Two", "Three"]);
+        let text_index = Arc::new(PdfTextIndex::new(&doc));
+        let job = GenerateOutlineJob::new(text_index, vec![vec![heading_font()]]);
+        assert_eq!(job.page_count(), 3);
+        let progress = Progress::new(job.page_count());
+
+        let (outline, steps) = run(job, &progress);
+        assert_eq!(steps, 4);
+        assert_eq!(progress.done(), 3);
+        let titles: Vec<&str> = outline.iter().map(|entry| entry.title.as_str()).collect();
+        assert_eq!(titles, ["One", "Two", "Three"]);
+    }
+
+    #[test]
+    fn saving_copies_inserts_and_serializes() {
+        let doc = Arc::new(document_with_headings(&["One", "Two"]));
+        let outline = vec![PdfOutlineEntry::new(2, String::from("Two"))];
+        let mut job = SaveJob::new(Arc::clone(&doc), outline.clone(), InsertMode::Replace);
+        let progress = Progress::new(job.step_count());
+
+        assert!(job.step(&progress).is_none());
+        assert!(job.step(&progress).is_none());
+        let data = job.step(&progress).unwrap().unwrap();
+        assert_eq!(progress.done(), job.step_count());
+
+        let saved = Document::load_mem(&data).unwrap();
+        assert_eq!(saved.read_outline().unwrap(), outline);
+        //the document shown in the app keeps its outline
+        assert!(doc.read_outline().unwrap().is_empty());
+    }
+
+    #[test]
+    fn failed_inserts_end_the_save() {
+        let doc = Arc::new(document_with_headings(&["One"]));
+        let outline = vec![PdfOutlineEntry::new(2, String::from("Missing"))];
+        let job = SaveJob::new(doc, outline, InsertMode::Replace);
+        let progress = Progress::new(job.step_count());
+
+        let (result, steps) = run(job, &progress);
+        assert!(matches!(result, Err(PdfError::Structure(_))));
+        assert_eq!(steps, 2);
+    }
+}
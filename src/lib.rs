@@ -1,6 +1,10 @@
 #![warn(clippy::all)]
 
 mod app;
+mod background;
+mod history;
+mod jobs;
+mod outline_editor;
 pub use app::App;
 
 pub mod pdf_tools;
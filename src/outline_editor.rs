@@ -0,0 +1,422 @@
+use egui::{collapsing_header::CollapsingState, CursorIcon, Rect, Sense};
+
+use crate::pdf_tools::pdf_outline::{PdfOutline, PdfOutlineEntry};
+
+/// The index of an entry at each level of the outline, from the top level down.
+type EntryPath = Vec<usize>;
+
+#[derive(Debug)]
+enum Edit {
+    /// A title was changed in place and is no longer being edited
+    Rename,
+    Delete(EntryPath),
+    Indent(EntryPath),
+    Outdent(EntryPath),
+    Move {
+        from: EntryPath,
+        to: EntryPath,
+    },
+    Add(PdfOutlineEntry),
+}
+
+/// Where an entry was drawn, used to find where a dragged entry is dropped.
+struct Row {
+    path: EntryPath,
+    rect: Rect,
+    /// Whether the children of the entry are shown below it
+    shows_children: bool,
+}
+
+/// Edits an outline in place: entries can be renamed, deleted, dragged to a new place,
+/// moved a level up or down, and added by hand.
+#[derive(Debug)]
+pub struct OutlineEditor {
+    page_count: u32,
+    dragged: Option<EntryPath>,
+    new_title: String,
+    new_page: u32,
+}
+
+impl OutlineEditor {
+    pub fn new(page_count: u32) -> Self {
+        Self {
+            page_count,
+            dragged: None,
+            new_title: String::new(),
+            new_page: 1,
+        }
+    }
+
+    /// Returns whether the outline was changed. A title counts as changed once it is no
+    /// longer being edited, so that typing a new title is a single change.
+    pub fn show(&mut self, ui: &mut egui::Ui, outline: &mut PdfOutline) -> bool {
+        //applied once everything is drawn, so the paths of the entries stay valid until then
+        let mut edit = None;
+        let mut rows = vec![];
+        Self::entries(
+            ui,
+            outline,
+            &mut vec![],
+            &mut self.dragged,
+            &mut rows,
+            &mut edit,
+        );
+        if let Some(drop) = self.drag_and_drop(ui, &rows) {
+            edit = Some(drop);
+        }
+
+        ui.separator();
+        self.new_entry(ui, &mut edit);
+
+        match edit {
+            Some(edit) => {
+                apply_edit(outline, edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops dragging an entry, for when the outline is replaced and the entry may be gone.
+    pub fn cancel_drag(&mut self) {
+        self.dragged = None;
+    }
+
+    fn entries(
+        ui: &mut egui::Ui,
+        entries: &mut [PdfOutlineEntry],
+        path: &mut EntryPath,
+        dragged: &mut Option<EntryPath>,
+        rows: &mut Vec<Row>,
+        edit: &mut Option<Edit>,
+    ) {
+        for (index, entry) in entries.iter_mut().enumerate() {
+            path.push(index);
+            if entry.children.is_empty() {
+                let response = ui
+                    .horizontal(|ui| {
+                        //line up with the titles of entries that have a collapse button
+                        let indent = ui.spacing().indent;
+                        ui.add_space(indent);
+                        Self::entry_row(
+                            ui,
+                            &mut entry.title,
+                            entry.page_number,
+                            path,
+                            dragged,
+                            edit,
+                        );
+                    })
+                    .response;
+                rows.push(Row {
+                    path: path.clone(),
+                    rect: response.rect,
+                    shows_children: false,
+                });
+            } else {
+                let id = ui.make_persistent_id(("Outline Entry", path.as_slice()));
+                let (_, header, body) = CollapsingState::load_with_default_open(ui.ctx(), id, true)
+                    .show_header(ui, |ui| {
+                        Self::entry_row(
+                            ui,
+                            &mut entry.title,
+                            entry.page_number,
+                            path,
+                            dragged,
+                            edit,
+                        );
+                    })
+                    .body(|ui| Self::entries(ui, &mut entry.children, path, dragged, rows, edit));
+                rows.push(Row {
+                    path: path.clone(),
+                    rect: header.response.rect,
+                    shows_children: body.is_some(),
+                });
+            }
+            path.pop();
+        }
+    }
+
+    fn entry_row(
+        ui: &mut egui::Ui,
+        title: &mut String,
+        page_number: u32,
+        path: &[usize],
+        dragged: &mut Option<EntryPath>,
+        edit: &mut Option<Edit>,
+    ) {
+        let handle = ui
+            .add(egui::Label::new("☰").sense(Sense::drag()))
+            .on_hover_cursor(CursorIcon::Grab)
+            .on_hover_text("Drag to move");
+        if handle.drag_started() {
+            *dragged = Some(path.to_vec());
+        }
+
+        if ui
+            .add(egui::TextEdit::singleline(title).desired_width(200.0))
+            .lost_focus()
+        {
+            *edit = Some(Edit::Rename);
+        }
+        ui.weak(format!("p. {}", page_number));
+
+        let is_nested = path.len() > 1;
+        let has_previous_sibling = path.last().map_or(false, |&index| index > 0);
+        if ui
+            .add_enabled(is_nested, egui::Button::new("⬅").small())
+            .on_hover_text("Move up a level")
+            .clicked()
+        {
+            *edit = Some(Edit::Outdent(path.to_vec()));
+        }
+        if ui
+            .add_enabled(has_previous_sibling, egui::Button::new("➡").small())
+            .on_hover_text("Move under the entry above")
+            .clicked()
+        {
+            *edit = Some(Edit::Indent(path.to_vec()));
+        }
+        if ui
+            .small_button("🗑")
+            .on_hover_text("Delete with its children")
+            .clicked()
+        {
+            *edit = Some(Edit::Delete(path.to_vec()));
+        }
+    }
+
+    /// Marks where the dragged entry would be dropped, and moves it there when released.
+    fn drag_and_drop(&mut self, ui: &egui::Ui, rows: &[Row]) -> Option<Edit> {
+        let from = self.dragged.as_ref()?;
+        ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+
+        //the gaps between rows count as part of the rows around them
+        let gap = egui::vec2(0.0, ui.spacing().item_spacing.y / 2.0);
+        let target = ui
+            .ctx()
+            .pointer_interact_pos()
+            .filter(|pos| ui.clip_rect().contains(*pos))
+            .and_then(|pos| {
+                let row = rows
+                    .iter()
+                    .find(|row| row.rect.expand2(gap).y_range().contains(pos.y))?;
+                let target = if pos.y < row.rect.center().y {
+                    (row.path.clone(), row.rect.top())
+                } else if row.shows_children {
+                    ([row.path.as_slice(), &[0]].concat(), row.rect.bottom())
+                } else {
+                    let mut path = row.path.clone();
+                    *path.last_mut()? += 1;
+                    (path, row.rect.bottom())
+                };
+                Some((target, row.rect))
+            })
+            //an entry can't be moved into itself
+            .filter(|((to, _), _)| !to.starts_with(from));
+
+        if let Some(((_, y), rect)) = &target {
+            ui.painter()
+                .hline(rect.x_range(), *y, ui.visuals().selection.stroke);
+        }
+
+        if ui.input(|input| input.pointer.any_released()) {
+            let from = self.dragged.take()?;
+            let ((to, _), _) = target?;
+            return Some(Edit::Move { from, to });
+        }
+        None
+    }
+
+    fn new_entry(&mut self, ui: &mut egui::Ui, edit: &mut Option<Edit>) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_title)
+                    .hint_text("New entry")
+                    .desired_width(200.0),
+            );
+            ui.label("Page");
+            ui.add(
+                egui::DragValue::new(&mut self.new_page).clamp_range(1..=self.page_count.max(1)),
+            );
+            let title = self.new_title.trim();
+            if ui
+                .add_enabled(!title.is_empty(), egui::Button::new("Add"))
+                .clicked()
+            {
+                *edit = Some(Edit::Add(PdfOutlineEntry::new(
+                    self.new_page,
+                    title.to_string(),
+                )));
+                self.new_title.clear();
+            }
+        });
+    }
+}
+
+fn apply_edit(outline: &mut PdfOutline, edit: Edit) {
+    match edit {
+        Edit::Rename => (),
+        Edit::Delete(path) => {
+            remove_entry(outline, &path);
+        }
+        Edit::Indent(path) => {
+            let Some((&index, parent)) = path.split_last() else {
+                return;
+            };
+            let siblings = children_mut(outline, parent);
+            if index > 0 {
+                let entry = siblings.remove(index);
+                siblings[index - 1].children.push(entry);
+            }
+        }
+        Edit::Outdent(path) => {
+            let [grandparent @ .., parent, index] = path.as_slice() else {
+                return;
+            };
+            let siblings = &mut children_mut(outline, grandparent)[*parent].children;
+            let mut entry = siblings.remove(*index);
+            //the siblings after the entry become its children to keep the reading order
+            entry.children.extend(siblings.drain(*index..));
+            children_mut(outline, grandparent).insert(parent + 1, entry);
+        }
+        Edit::Move { from, mut to } => {
+            let Some(entry) = remove_entry(outline, &from) else {
+                return;
+            };
+            //removing the entry shifts the target up if it came after it at the same level
+            let depth = from.len() - 1;
+            if to.len() > depth && to[..depth] == from[..depth] && to[depth] > from[depth] {
+                to[depth] -= 1;
+            }
+            let Some((&index, parent)) = to.split_last() else {
+                return;
+            };
+            let siblings = children_mut(outline, parent);
+            siblings.insert(index.min(siblings.len()), entry);
+        }
+        Edit::Add(entry) => {
+            //keep the top level in page order
+            let index = outline
+                .iter()
+                .position(|other| other.page_number > entry.page_number)
+                .unwrap_or(outline.len());
+            outline.insert(index, entry);
+        }
+    }
+}
+
+fn children_mut<'a>(outline: &'a mut PdfOutline, path: &[usize]) -> &'a mut PdfOutline {
+    path.iter()
+        .fold(outline, |entries, &index| &mut entries[index].children)
+}
+
+fn remove_entry(outline: &mut PdfOutline, path: &[usize]) -> Option<PdfOutlineEntry> {
+    let (&index, parent) = path.split_last()?;
+    Some(children_mut(outline, parent).remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, page_number: u32, children: Vec<PdfOutlineEntry>) -> PdfOutlineEntry {
+        let mut entry = PdfOutlineEntry::new(page_number, title.to_string());
+        entry.children = children;
+        entry
+    }
+
+    /// The titles of an outline, with children in parentheses.
+    fn titles(outline: &PdfOutline) -> String {
+        outline
+            .iter()
+            .map(|entry| match entry.children.is_empty() {
+                true => entry.title.clone(),
+                false => format!("{}({})", entry.title, titles(&entry.children)),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn sample() -> PdfOutline {
+        vec![
+            entry("A", 1, vec![entry("B", 2, vec![]), entry("C", 3, vec![])]),
+            entry("D", 4, vec![]),
+            entry("E", 5, vec![]),
+        ]
+    }
+
+    fn edited(edit: Edit) -> String {
+        let mut outline = sample();
+        apply_edit(&mut outline, edit);
+        titles(&outline)
+    }
+
+    #[test]
+    fn delete_removes_children_too() {
+        assert_eq!(edited(Edit::Delete(vec![0, 1])), "A(B) D E");
+        assert_eq!(edited(Edit::Delete(vec![0])), "D E");
+    }
+
+    #[test]
+    fn indent_nests_under_previous_sibling() {
+        assert_eq!(edited(Edit::Indent(vec![1])), "A(B C D) E");
+        assert_eq!(edited(Edit::Indent(vec![0, 1])), "A(B(C)) D E");
+        //the first entry has no sibling to nest under
+        assert_eq!(edited(Edit::Indent(vec![0])), "A(B C) D E");
+    }
+
+    #[test]
+    fn outdent_of_last_child_follows_parent() {
+        assert_eq!(edited(Edit::Outdent(vec![0, 1])), "A(B) C D E");
+    }
+
+    #[test]
+    fn outdent_adopts_following_siblings() {
+        assert_eq!(edited(Edit::Outdent(vec![0, 0])), "A B(C) D E");
+        //top level entries can't be outdented
+        assert_eq!(edited(Edit::Outdent(vec![1])), "A(B C) D E");
+    }
+
+    #[test]
+    fn move_to_later_sibling_accounts_for_removal() {
+        assert_eq!(
+            edited(Edit::Move {
+                from: vec![0],
+                to: vec![2],
+            }),
+            "D A(B C) E"
+        );
+        assert_eq!(
+            edited(Edit::Move {
+                from: vec![0],
+                to: vec![3],
+            }),
+            "D E A(B C)"
+        );
+    }
+
+    #[test]
+    fn move_to_earlier_or_nested_position() {
+        assert_eq!(
+            edited(Edit::Move {
+                from: vec![2],
+                to: vec![0, 1],
+            }),
+            "A(B E C) D"
+        );
+        assert_eq!(
+            edited(Edit::Move {
+                from: vec![0, 0],
+                to: vec![1, 0],
+            }),
+            "A(C) D(B) E"
+        );
+    }
+
+    #[test]
+    fn add_keeps_top_level_in_page_order() {
+        assert_eq!(edited(Edit::Add(entry("F", 4, vec![]))), "A(B C) D F E");
+        assert_eq!(edited(Edit::Add(entry("G", 9, vec![]))), "A(B C) D E G");
+    }
+}
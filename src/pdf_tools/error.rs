@@ -0,0 +1,102 @@
+use std::fmt;
+
+/// Everything that can go wrong reading a PDF or writing one with an outline.
+#[derive(Debug)]
+pub enum PdfError {
+    /// The file doesn't start with a PDF header
+    NotPdf,
+    /// The header names a version of PDF that isn't supported
+    UnsupportedVersion(String),
+    /// The file looks like a PDF, but is damaged and could not be parsed
+    Load(lopdf::Error),
+    /// The file is encrypted, and can't be read without a password
+    Encrypted,
+    /// The file's owner forbids these, which a saved copy would no longer do
+    Restricted(Vec<&'static str>),
+    /// A page's content stream could not be decoded, or an operator in it has invalid operands
+    MalformedContent {
+        page_number: u32,
+        source: lopdf::Error,
+    },
+    /// A page selects a font that is not in its resources
+    MissingFontResource { page_number: u32, resource: String },
+    /// A page selects a font whose name could not be resolved
+    UnnamedFont { page_number: u32, resource: String },
+    /// An object the document needs is missing or has the wrong type
+    Structure(lopdf::Error),
+    /// The document could not be written
+    Save(std::io::Error),
+}
+
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdfError::NotPdf => write!(f, "the file is not a PDF"),
+            PdfError::UnsupportedVersion(version) if version.is_empty() => {
+                write!(f, "the PDF header has no version")
+            }
+            PdfError::UnsupportedVersion(version) => {
+                write!(f, "PDF version {} is not supported", version)
+            }
+            PdfError::Load(e) => write!(f, "the PDF is corrupt: {}", e),
+            PdfError::Encrypted => write!(
+                f,
+                "the PDF is encrypted and can't be read without its password"
+            ),
+            PdfError::Restricted(restrictions) => write!(
+                f,
+                "the PDF's owner restricts {}, which a copy saved with an outline wouldn't",
+                restrictions.join(", ")
+            ),
+            PdfError::MalformedContent {
+                page_number,
+                source,
+            } => write!(
+                f,
+                "page {}: malformed content stream: {}",
+                page_number, source
+            ),
+            PdfError::MissingFontResource {
+                page_number,
+                resource,
+            } => write!(
+                f,
+                "page {}: font /{} is missing from the page resources, its text is skipped",
+                page_number, resource
+            ),
+            PdfError::UnnamedFont {
+                page_number,
+                resource,
+            } => write!(
+                f,
+                "page {}: font /{} has no name that could be resolved, its text is skipped",
+                page_number, resource
+            ),
+            PdfError::Structure(e) => write!(f, "invalid document structure: {}", e),
+            PdfError::Save(e) => write!(f, "the PDF could not be written: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PdfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PdfError::Load(e)
+            | PdfError::MalformedContent { source: e, .. }
+            | PdfError::Structure(e) => Some(e),
+            PdfError::Save(e) => Some(e),
+            PdfError::NotPdf
+            | PdfError::UnsupportedVersion(_)
+            | PdfError::Encrypted
+            | PdfError::Restricted(_)
+            | PdfError::MissingFontResource { .. }
+            | PdfError::UnnamedFont { .. } => None,
+        }
+    }
+}
+
+impl From<lopdf::Error> for PdfError {
+    fn from(e: lopdf::Error) -> Self {
+        PdfError::Structure(e)
+    }
+}
@@ -0,0 +1,834 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Converts the bytes of a string shown with a font into Unicode text.
+///
+/// The font's `ToUnicode` CMap is used when present. Otherwise codes are looked up in the
+/// font's `Encoding`, either a predefined encoding name or a dictionary with a
+/// `Differences` array, falling back to `StandardEncoding` like lopdf does.
+#[derive(Debug, Default)]
+pub struct FontDecoder {
+    to_unicode: Option<ToUnicodeCMap>,
+    encoding: FontEncoding,
+}
+
+#[derive(Debug)]
+enum FontEncoding {
+    /// One byte per code, mapped through a 256 entry table
+    Simple(Vec<Option<String>>),
+    /// Two bytes per code, which are UTF-16BE code units
+    Utf16,
+    /// Two bytes per code, which are CIDs that cannot be mapped without a `ToUnicode` CMap
+    Cid,
+}
+
+impl Default for FontEncoding {
+    fn default() -> Self {
+        FontEncoding::Simple(simple_encoding_table("StandardEncoding").to_vec())
+    }
+}
+
+impl FontDecoder {
+    pub fn new(doc: &Document, font: &Dictionary) -> Self {
+        let to_unicode = font
+            .get_deref(b"ToUnicode", doc)
+            .and_then(Object::as_stream)
+            .ok()
+            .map(|stream| {
+                let content = stream
+                    .decompressed_content()
+                    .unwrap_or_else(|_| stream.content.clone());
+                ToUnicodeCMap::parse(&content)
+            })
+            .filter(|cmap| !cmap.is_empty());
+
+        let is_composite = font.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type0");
+        let encoding = if is_composite {
+            match font.get_font_encoding() {
+                name if name.contains("UCS2") || name.contains("UTF16") => FontEncoding::Utf16,
+                _ => FontEncoding::Cid,
+            }
+        } else {
+            simple_font_encoding(doc, font)
+        };
+
+        FontDecoder {
+            to_unicode,
+            encoding,
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let length = match (&self.to_unicode, &self.encoding) {
+                (Some(cmap), _) if cmap.has_codespace() => cmap.code_length(rest),
+                (_, FontEncoding::Simple(_)) => 1,
+                _ => 2,
+            }
+            .min(rest.len());
+            let (code, remainder) = rest.split_at(length);
+            rest = remainder;
+
+            if let Some(mapped) = self.to_unicode.as_ref().and_then(|cmap| cmap.lookup(code)) {
+                text.push_str(&mapped);
+                continue;
+            }
+            match &self.encoding {
+                FontEncoding::Simple(table) => {
+                    for &byte in code {
+                        if let Some(mapped) = &table[byte as usize] {
+                            text.push_str(mapped);
+                        }
+                    }
+                }
+                FontEncoding::Utf16 => {
+                    let units: Vec<u16> = code
+                        .chunks(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+                        .collect();
+                    text.push_str(&String::from_utf16_lossy(&units));
+                }
+                FontEncoding::Cid => text.push(char::REPLACEMENT_CHARACTER),
+            }
+        }
+        text
+    }
+}
+
+/// The decoders of a document's fonts by object id, shared by the pages reading
+/// its text, since most fonts are used on many pages.
+#[derive(Debug, Default)]
+pub struct FontDecoders {
+    decoders: Mutex<BTreeMap<ObjectId, Arc<FontDecoder>>>,
+}
+
+impl FontDecoders {
+    /// Returns the decoder of the font `font_id`, which is `font`, building it the first time.
+    pub fn get(&self, doc: &Document, font_id: ObjectId, font: &Dictionary) -> Arc<FontDecoder> {
+        if let Some(decoder) = self.lock().get(&font_id) {
+            return Arc::clone(decoder);
+        }
+        //built without holding the lock, so that pages read in parallel don't wait on it
+        let decoder = Arc::new(FontDecoder::new(doc, font));
+        Arc::clone(self.lock().entry(font_id).or_insert(decoder))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<ObjectId, Arc<FontDecoder>>> {
+        //the map is only inserted into, so it is whole even if a reader panicked
+        self.decoders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn simple_font_encoding(doc: &Document, font: &Dictionary) -> FontEncoding {
+    match font.get_deref(b"Encoding", doc) {
+        Ok(Object::Name(name)) => {
+            FontEncoding::Simple(simple_encoding_table(&String::from_utf8_lossy(name)).to_vec())
+        }
+        Ok(Object::Dictionary(encoding)) => {
+            let base_encoding = encoding
+                .get(b"BaseEncoding")
+                .and_then(Object::as_name_str)
+                .unwrap_or("StandardEncoding");
+            let mut table = simple_encoding_table(base_encoding).to_vec();
+            if let Ok(differences) = encoding.get_deref(b"Differences", doc) {
+                apply_differences(&mut table, differences);
+            }
+            FontEncoding::Simple(table)
+        }
+        _ => FontEncoding::default(),
+    }
+}
+
+/// Returns the code to text table of a predefined simple font encoding, built
+/// the first time it is used. lopdf decodes other names as UTF-8.
+fn simple_encoding_table(name: &str) -> &'static [Option<String>] {
+    static STANDARD: OnceLock<Vec<Option<String>>> = OnceLock::new();
+    static MAC_ROMAN: OnceLock<Vec<Option<String>>> = OnceLock::new();
+    static MAC_EXPERT: OnceLock<Vec<Option<String>>> = OnceLock::new();
+    static WIN_ANSI: OnceLock<Vec<Option<String>>> = OnceLock::new();
+    static UNKNOWN: OnceLock<Vec<Option<String>>> = OnceLock::new();
+
+    let (table, name) = match name {
+        "StandardEncoding" => (&STANDARD, Some(name)),
+        "MacRomanEncoding" => (&MAC_ROMAN, Some(name)),
+        "MacExpertEncoding" => (&MAC_EXPERT, Some(name)),
+        "WinAnsiEncoding" => (&WIN_ANSI, Some(name)),
+        _ => (&UNKNOWN, None),
+    };
+    table.get_or_init(|| {
+        (0..=255u8)
+            .map(|byte| {
+                let text = match name {
+                    Some(name) => Document::decode_text(Some(name), &[byte]),
+                    None => String::from_utf8_lossy(&[byte]).into_owned(),
+                };
+                Some(text).filter(|text| !text.is_empty())
+            })
+            .collect()
+    })
+}
+
+/// Applies a `Differences` array, a code followed by the glyph names of consecutive codes.
+fn apply_differences(table: &mut [Option<String>], differences: &Object) {
+    let Ok(differences) = differences.as_array() else {
+        return;
+    };
+    let mut code = 0;
+    for item in differences {
+        match item {
+            Object::Integer(start) => code = *start as usize,
+            Object::Name(glyph_name) => {
+                if let Some(entry) = table.get_mut(code) {
+                    *entry = glyph_name_to_text(&String::from_utf8_lossy(glyph_name));
+                }
+                code += 1;
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Maps a glyph name to text following the Adobe Glyph List conventions for the names
+/// commonly found in `Differences` arrays.
+fn glyph_name_to_text(glyph_name: &str) -> Option<String> {
+    // Suffixes such as ".sc" or ".alt" name variants of the same character
+    let glyph_name = glyph_name.split('.').next().unwrap_or(glyph_name);
+    if glyph_name.contains('_') {
+        return glyph_name
+            .split('_')
+            .map(glyph_name_to_text)
+            .collect::<Option<String>>();
+    }
+
+    if let Some(hex) = glyph_name.strip_prefix("uni") {
+        //names that aren't ASCII can't be hexadecimal, and can't be split by byte
+        if hex.is_ascii() && hex.len() % 4 == 0 && !hex.is_empty() {
+            let units = (0..hex.len())
+                .step_by(4)
+                .map(|i| u16::from_str_radix(&hex[i..i + 4], 16).ok())
+                .collect::<Option<Vec<u16>>>()?;
+            return Some(String::from_utf16_lossy(&units));
+        }
+    }
+    if let Some(hex) = glyph_name.strip_prefix('u') {
+        if (4..=6).contains(&hex.len()) {
+            if let Some(c) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                return Some(c.to_string());
+            }
+        }
+    }
+
+    let mut chars = glyph_name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_string());
+        }
+    }
+
+    GLYPH_NAMES
+        .binary_search_by(|(name, _)| name.cmp(&glyph_name))
+        .ok()
+        .map(|index| GLYPH_NAMES[index].1.to_string())
+}
+
+/// Glyph names of the standard Latin character sets, sorted by name.
+const GLYPH_NAMES: &[(&str, &str)] = &[
+    ("AE", "Æ"),
+    ("Aacute", "Á"),
+    ("Acircumflex", "Â"),
+    ("Adieresis", "Ä"),
+    ("Agrave", "À"),
+    ("Aring", "Å"),
+    ("Atilde", "Ã"),
+    ("Ccedilla", "Ç"),
+    ("Eacute", "É"),
+    ("Ecircumflex", "Ê"),
+    ("Edieresis", "Ë"),
+    ("Egrave", "È"),
+    ("Eth", "Ð"),
+    ("Euro", "€"),
+    ("Iacute", "Í"),
+    ("Icircumflex", "Î"),
+    ("Idieresis", "Ï"),
+    ("Igrave", "Ì"),
+    ("Lslash", "Ł"),
+    ("Ntilde", "Ñ"),
+    ("OE", "Œ"),
+    ("Oacute", "Ó"),
+    ("Ocircumflex", "Ô"),
+    ("Odieresis", "Ö"),
+    ("Ograve", "Ò"),
+    ("Oslash", "Ø"),
+    ("Otilde", "Õ"),
+    ("Scaron", "Š"),
+    ("Thorn", "Þ"),
+    ("Uacute", "Ú"),
+    ("Ucircumflex", "Û"),
+    ("Udieresis", "Ü"),
+    ("Ugrave", "Ù"),
+    ("Yacute", "Ý"),
+    ("Ydieresis", "Ÿ"),
+    ("Zcaron", "Ž"),
+    ("aacute", "á"),
+    ("acircumflex", "â"),
+    ("acute", "´"),
+    ("adieresis", "ä"),
+    ("ae", "æ"),
+    ("agrave", "à"),
+    ("ampersand", "&"),
+    ("aring", "å"),
+    ("asciicircum", "^"),
+    ("asciitilde", "~"),
+    ("asterisk", "*"),
+    ("at", "@"),
+    ("atilde", "ã"),
+    ("backslash", "\\"),
+    ("bar", "|"),
+    ("braceleft", "{"),
+    ("braceright", "}"),
+    ("bracketleft", "["),
+    ("bracketright", "]"),
+    ("brokenbar", "¦"),
+    ("bullet", "•"),
+    ("ccedilla", "ç"),
+    ("cedilla", "¸"),
+    ("cent", "¢"),
+    ("circumflex", "ˆ"),
+    ("colon", ":"),
+    ("comma", ","),
+    ("copyright", "©"),
+    ("currency", "¤"),
+    ("dagger", "†"),
+    ("daggerdbl", "‡"),
+    ("degree", "°"),
+    ("dieresis", "¨"),
+    ("divide", "÷"),
+    ("dollar", "$"),
+    ("dotlessi", "ı"),
+    ("eacute", "é"),
+    ("ecircumflex", "ê"),
+    ("edieresis", "ë"),
+    ("egrave", "è"),
+    ("eight", "8"),
+    ("ellipsis", "…"),
+    ("emdash", "—"),
+    ("endash", "–"),
+    ("equal", "="),
+    ("eth", "ð"),
+    ("exclam", "!"),
+    ("exclamdown", "¡"),
+    ("ff", "ff"),
+    ("ffi", "ffi"),
+    ("ffl", "ffl"),
+    ("fi", "fi"),
+    ("five", "5"),
+    ("fl", "fl"),
+    ("florin", "ƒ"),
+    ("four", "4"),
+    ("fraction", "⁄"),
+    ("germandbls", "ß"),
+    ("grave", "`"),
+    ("greater", ">"),
+    ("guillemotleft", "«"),
+    ("guillemotright", "»"),
+    ("guilsinglleft", "‹"),
+    ("guilsinglright", "›"),
+    ("hyphen", "-"),
+    ("iacute", "í"),
+    ("icircumflex", "î"),
+    ("idieresis", "ï"),
+    ("igrave", "ì"),
+    ("less", "<"),
+    ("logicalnot", "¬"),
+    ("lslash", "ł"),
+    ("macron", "¯"),
+    ("minus", "−"),
+    ("mu", "µ"),
+    ("multiply", "×"),
+    ("nine", "9"),
+    ("nonbreakingspace", "\u{a0}"),
+    ("ntilde", "ñ"),
+    ("numbersign", "#"),
+    ("oacute", "ó"),
+    ("ocircumflex", "ô"),
+    ("odieresis", "ö"),
+    ("oe", "œ"),
+    ("ograve", "ò"),
+    ("one", "1"),
+    ("onehalf", "½"),
+    ("onequarter", "¼"),
+    ("ordfeminine", "ª"),
+    ("ordmasculine", "º"),
+    ("oslash", "ø"),
+    ("otilde", "õ"),
+    ("paragraph", "¶"),
+    ("parenleft", "("),
+    ("parenright", ")"),
+    ("percent", "%"),
+    ("period", "."),
+    ("periodcentered", "·"),
+    ("perthousand", "‰"),
+    ("plus", "+"),
+    ("plusminus", "±"),
+    ("question", "?"),
+    ("questiondown", "¿"),
+    ("quotedbl", "\""),
+    ("quotedblbase", "„"),
+    ("quotedblleft", "“"),
+    ("quotedblright", "”"),
+    ("quoteleft", "‘"),
+    ("quoteright", "’"),
+    ("quotesinglbase", "‚"),
+    ("quotesingle", "'"),
+    ("registered", "®"),
+    ("scaron", "š"),
+    ("section", "§"),
+    ("semicolon", ";"),
+    ("seven", "7"),
+    ("six", "6"),
+    ("slash", "/"),
+    ("space", " "),
+    ("sterling", "£"),
+    ("thorn", "þ"),
+    ("three", "3"),
+    ("threequarters", "¾"),
+    ("tilde", "˜"),
+    ("trademark", "™"),
+    ("two", "2"),
+    ("uacute", "ú"),
+    ("ucircumflex", "û"),
+    ("udieresis", "ü"),
+    ("ugrave", "ù"),
+    ("underscore", "_"),
+    ("yacute", "ý"),
+    ("ydieresis", "ÿ"),
+    ("yen", "¥"),
+    ("zcaron", "ž"),
+    ("zero", "0"),
+];
+
+/// The code to Unicode mappings of a `ToUnicode` CMap stream.
+#[derive(Debug, Default)]
+struct ToUnicodeCMap {
+    /// Inclusive `(low, high)` code ranges, which determine how many bytes each code uses
+    codespace: Vec<(Vec<u8>, Vec<u8>)>,
+    chars: BTreeMap<Vec<u8>, String>,
+    /// Inclusive `(low, high)` code ranges and the UTF-16 text of their first code
+    ranges: Vec<(Vec<u8>, Vec<u8>, RangeDestination)>,
+}
+
+#[derive(Debug)]
+enum RangeDestination {
+    /// Text of the first code, incremented for each following code
+    Offset(Vec<u16>),
+    /// Text of every code in the range
+    List(Vec<String>),
+}
+
+#[derive(Debug, PartialEq)]
+enum CMapToken {
+    Hex(Vec<u8>),
+    Name(String),
+    ArrayStart,
+    ArrayEnd,
+    Keyword(String),
+}
+
+impl ToUnicodeCMap {
+    fn parse(content: &[u8]) -> Self {
+        let tokens = tokenize_cmap(content);
+        let mut cmap = ToUnicodeCMap::default();
+        let mut i = 0;
+        while i < tokens.len() {
+            let CMapToken::Keyword(keyword) = &tokens[i] else {
+                i += 1;
+                continue;
+            };
+            i += 1;
+            match keyword.as_str() {
+                "begincodespacerange" => {
+                    while let [CMapToken::Hex(low), CMapToken::Hex(high), ..] = &tokens[i..] {
+                        //a range without codes, or with bounds of different lengths, can't match
+                        if !low.is_empty() && low.len() == high.len() {
+                            cmap.codespace.push((low.clone(), high.clone()));
+                        }
+                        i += 2;
+                    }
+                }
+                "beginbfchar" => loop {
+                    match &tokens[i..] {
+                        [CMapToken::Hex(code), CMapToken::Hex(text), ..] => {
+                            cmap.chars.insert(code.clone(), utf16_be_to_string(text));
+                        }
+                        [CMapToken::Hex(code), CMapToken::Name(glyph_name), ..] => {
+                            if let Some(text) = glyph_name_to_text(glyph_name) {
+                                cmap.chars.insert(code.clone(), text);
+                            }
+                        }
+                        _ => break,
+                    }
+                    i += 2;
+                },
+                "beginbfrange" => loop {
+                    match &tokens[i..] {
+                        [CMapToken::Hex(low), CMapToken::Hex(high), CMapToken::Hex(text), ..] => {
+                            let units = utf16_be_units(text);
+                            cmap.ranges.push((
+                                low.clone(),
+                                high.clone(),
+                                RangeDestination::Offset(units),
+                            ));
+                            i += 3;
+                        }
+                        [CMapToken::Hex(low), CMapToken::Hex(high), CMapToken::ArrayStart, ..] => {
+                            let mut texts = vec![];
+                            i += 3;
+                            while let Some(CMapToken::Hex(text)) = tokens.get(i) {
+                                texts.push(utf16_be_to_string(text));
+                                i += 1;
+                            }
+                            if tokens.get(i) == Some(&CMapToken::ArrayEnd) {
+                                i += 1;
+                            }
+                            cmap.ranges.push((
+                                low.clone(),
+                                high.clone(),
+                                RangeDestination::List(texts),
+                            ));
+                        }
+                        _ => break,
+                    }
+                },
+                _ => (),
+            }
+        }
+        cmap
+    }
+
+    fn is_empty(&self) -> bool {
+        self.chars.is_empty() && self.ranges.is_empty()
+    }
+
+    fn has_codespace(&self) -> bool {
+        !self.codespace.is_empty()
+    }
+
+    /// Returns the length of the code at the start of `bytes`, the shortest length
+    /// which falls within a codespace range. Codes are at least one byte long,
+    /// so that decoding always makes progress.
+    fn code_length(&self, bytes: &[u8]) -> usize {
+        let max_length = self
+            .codespace
+            .iter()
+            .map(|(low, _)| low.len())
+            .max()
+            .unwrap_or(1);
+        (1..=max_length.min(bytes.len()))
+            .find(|&length| {
+                let code = &bytes[..length];
+                self.codespace.iter().any(|(low, high)| {
+                    low.len() == length
+                        && code
+                            .iter()
+                            .zip(low)
+                            .zip(high)
+                            .all(|((c, l), h)| l <= c && c <= h)
+                })
+            })
+            .unwrap_or_else(|| max_length.min(bytes.len()))
+            .max(1)
+    }
+
+    fn lookup(&self, code: &[u8]) -> Option<String> {
+        if let Some(text) = self.chars.get(code) {
+            return Some(text.clone());
+        }
+        let value = code_value(code);
+        self.ranges.iter().find_map(|(low, high, destination)| {
+            if low.len() != code.len() || value < code_value(low) || value > code_value(high) {
+                return None;
+            }
+            let offset = value - code_value(low);
+            match destination {
+                RangeDestination::Offset(units) => {
+                    let mut units = units.clone();
+                    let last = units.last_mut()?;
+                    *last = last.wrapping_add(offset as u16);
+                    Some(String::from_utf16_lossy(&units))
+                }
+                RangeDestination::List(texts) => texts.get(offset as usize).cloned(),
+            }
+        })
+    }
+}
+
+fn code_value(code: &[u8]) -> u32 {
+    code.iter()
+        .fold(0, |value, &byte| (value << 8) | byte as u32)
+}
+
+fn utf16_be_units(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks(2)
+        //a trailing single byte is kept as a unit of its own
+        .map(|pair| pair.iter().fold(0, |unit, &byte| (unit << 8) | byte as u16))
+        .collect()
+}
+
+fn utf16_be_to_string(bytes: &[u8]) -> String {
+    String::from_utf16_lossy(&utf16_be_units(bytes))
+}
+
+/// Splits a CMap into the tokens needed to read its mappings, skipping everything
+/// else such as comments, numbers, strings and dictionaries.
+fn tokenize_cmap(content: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'<' if content.get(i + 1) == Some(&b'<') => i += 2,
+            b'>' if content.get(i + 1) == Some(&b'>') => i += 2,
+            b'<' => {
+                let digits: Vec<u8> = content[i + 1..]
+                    .iter()
+                    .take_while(|&&c| c != b'>')
+                    .filter(|c| c.is_ascii_hexdigit())
+                    .copied()
+                    .collect();
+                i += content[i + 1..]
+                    .iter()
+                    .position(|&c| c == b'>')
+                    .map_or(content.len(), |end| end + 2);
+                tokens.push(CMapToken::Hex(hex_to_bytes(&digits)));
+            }
+            b'[' => {
+                tokens.push(CMapToken::ArrayStart);
+                i += 1;
+            }
+            b']' => {
+                tokens.push(CMapToken::ArrayEnd);
+                i += 1;
+            }
+            b'(' => {
+                // Literal strings only appear in the CIDSystemInfo, skip them
+                let mut depth = 0;
+                while i < content.len() {
+                    match content[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => (),
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            c if is_cmap_delimiter(c) => i += 1,
+            _ => {
+                //a name ends where the next one starts, as in `/A/B`
+                let start = i;
+                i += 1;
+                while i < content.len() && !is_cmap_delimiter(content[i]) && content[i] != b'/' {
+                    i += 1;
+                }
+                let word = String::from_utf8_lossy(&content[start..i]);
+                if let Some(name) = word.strip_prefix('/') {
+                    tokens.push(CMapToken::Name(name.to_string()));
+                } else if word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    tokens.push(CMapToken::Keyword(word.into_owned()));
+                }
+            }
+        }
+    }
+    tokens
+}
+
+fn is_cmap_delimiter(c: u8) -> bool {
+    c.is_ascii_whitespace() || b"<>[](){}%".contains(&c) || c == 0
+}
+
+fn hex_to_bytes(digits: &[u8]) -> Vec<u8> {
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let digit = |c: u8| (c as char).to_digit(16).unwrap_or(0) as u8;
+            // A final odd digit is followed by an implicit 0
+            digit(pair[0]) << 4 | pair.get(1).map_or(0, |&c| digit(c))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+
+    use super::*;
+
+    fn decoder_with_cmap(cmap: &[u8]) -> FontDecoder {
+        FontDecoder {
+            to_unicode: Some(ToUnicodeCMap::parse(cmap)),
+            encoding: FontEncoding::default(),
+        }
+    }
+
+    #[test]
+    fn bfchar_maps_single_codes() {
+        let decoder = decoder_with_cmap(
+            b"/CIDInit /ProcSet findresource begin
+            1 begincodespacerange <00> <FF> endcodespacerange
+            2 beginbfchar <01> <0048> <02> <00690021> endbfchar
+            endcmap",
+        );
+        assert_eq!(decoder.decode(&[0x01, 0x02]), "Hi!");
+    }
+
+    #[test]
+    fn bfrange_offsets_the_first_code() {
+        let decoder = decoder_with_cmap(
+            b"1 begincodespacerange <00> <FF> endcodespacerange
+            1 beginbfrange <20> <22> <0061> endbfrange",
+        );
+        assert_eq!(decoder.decode(&[0x20, 0x21, 0x22]), "abc");
+    }
+
+    #[test]
+    fn bfrange_array_lists_every_code() {
+        let decoder = decoder_with_cmap(
+            b"1 begincodespacerange <00> <FF> endcodespacerange
+            1 beginbfrange <30> <31> [<0058> <00590059>] endbfrange",
+        );
+        assert_eq!(decoder.decode(&[0x30, 0x31]), "XYY");
+    }
+
+    #[test]
+    fn multi_byte_codespace_splits_codes() {
+        let decoder = decoder_with_cmap(
+            b"2 begincodespacerange <00> <7F> <8000> <FFFF> endcodespacerange
+            2 beginbfchar <41> <0041> <8001> <4E00> endbfchar",
+        );
+        assert_eq!(decoder.decode(&[0x41, 0x80, 0x01, 0x41]), "A\u{4e00}A");
+    }
+
+    #[test]
+    fn names_split_at_slashes() {
+        assert_eq!(
+            tokenize_cmap(b"/A/B /C"),
+            vec![
+                CMapToken::Name(String::from("A")),
+                CMapToken::Name(String::from("B")),
+                CMapToken::Name(String::from("C")),
+            ]
+        );
+    }
+
+    #[test]
+    fn differences_replace_base_encoding() {
+        let mut table = simple_encoding_table("StandardEncoding").to_vec();
+        let differences = Object::Array(vec![
+            Object::Integer(65),
+            Object::Name(b"Eacute".to_vec()),
+            Object::Name(b"uni0042".to_vec()),
+            Object::Integer(100),
+            Object::Name(b"f_i".to_vec()),
+            Object::Name(b"a.sc".to_vec()),
+        ]);
+        apply_differences(&mut table, &differences);
+        assert_eq!(table[65].as_deref(), Some("É"));
+        assert_eq!(table[66].as_deref(), Some("B"));
+        assert_eq!(table[67].as_deref(), Some("C"));
+        assert_eq!(table[100].as_deref(), Some("fi"));
+        assert_eq!(table[101].as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn encoding_tables_are_built_once() {
+        let standard = simple_encoding_table("StandardEncoding");
+        assert!(std::ptr::eq(
+            standard,
+            simple_encoding_table("StandardEncoding")
+        ));
+        assert_eq!(
+            simple_encoding_table("WinAnsiEncoding")[0x80].as_deref(),
+            Some("€")
+        );
+        assert_eq!(
+            simple_encoding_table("Custom")[b'A' as usize].as_deref(),
+            Some("A")
+        );
+    }
+
+    #[test]
+    fn decoders_are_shared_by_font_id() {
+        let doc = Document::with_version("1.5");
+        let font = dictionary! { "Type" => "Font", "Subtype" => "Type1" };
+        let decoders = FontDecoders::default();
+        let first = decoders.get(&doc, (1, 0), &font);
+        assert!(Arc::ptr_eq(&first, &decoders.get(&doc, (1, 0), &font)));
+        assert!(!Arc::ptr_eq(&first, &decoders.get(&doc, (2, 0), &font)));
+    }
+
+    #[test]
+    fn glyph_names_map_to_text() {
+        assert_eq!(glyph_name_to_text("endash").as_deref(), Some("–"));
+        assert_eq!(glyph_name_to_text("u1F600").as_deref(), Some("\u{1f600}"));
+        assert_eq!(glyph_name_to_text("uni00410042").as_deref(), Some("AB"));
+        assert_eq!(glyph_name_to_text("unknownglyph"), None);
+    }
+
+    #[test]
+    fn non_ascii_uni_names_are_not_hexadecimal() {
+        //`/uniab#FF123`, which is eight bytes once the invalid byte is replaced
+        let glyph_name = String::from_utf8_lossy(b"uniab\xFF123");
+        assert_eq!(glyph_name.strip_prefix("uni").unwrap().len(), 8);
+        assert_eq!(glyph_name_to_text(&glyph_name), None);
+        assert_eq!(glyph_name_to_text("u\u{e9}\u{e9}"), None);
+    }
+
+    #[test]
+    fn glyph_name_table_is_sorted() {
+        assert!(GLYPH_NAMES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn empty_codespace_range_is_ignored() {
+        let decoder = decoder_with_cmap(
+            b"1 begincodespacerange <> <> endcodespacerange
+            1 beginbfchar <01> <0041> endbfchar",
+        );
+        assert!(!decoder.to_unicode.as_ref().unwrap().has_codespace());
+        assert_eq!(decoder.decode(&[0x01, 0x01]), "AA");
+    }
+
+    #[test]
+    fn codespace_ranges_of_mismatched_lengths_are_ignored() {
+        let cmap = ToUnicodeCMap::parse(b"1 begincodespacerange <00> <FFFF> endcodespacerange");
+        assert!(!cmap.has_codespace());
+    }
+
+    #[test]
+    fn codes_are_at_least_one_byte_long() {
+        let cmap = ToUnicodeCMap {
+            codespace: vec![(vec![], vec![])],
+            ..Default::default()
+        };
+        assert_eq!(cmap.code_length(&[0x41, 0x42]), 1);
+    }
+}
@@ -0,0 +1,326 @@
+use lopdf::{content::Operation, Error};
+
+pub const SAVE_STATE: &str = "q";
+pub const RESTORE_STATE: &str = "Q";
+pub const CONCAT_MATRIX: &str = "cm";
+pub const BEGIN_TEXT: &str = "BT";
+pub const END_TEXT: &str = "ET";
+pub const SET_TEXT_FONT: &str = "Tf";
+pub const SET_TEXT_MATRIX: &str = "Tm";
+pub const SET_HORIZONTAL_SCALING: &str = "Tz";
+pub const SET_TEXT_LEADING: &str = "TL";
+pub const MOVE_TEXT: &str = "Td";
+pub const MOVE_TEXT_SET_LEADING: &str = "TD";
+pub const NEXT_LINE: &str = "T*";
+pub const NEXT_LINE_SHOW_TEXT: &str = "'";
+pub const NEXT_LINE_SHOW_SPACED_TEXT: &str = "\"";
+pub const STATE_OPS: [&str; 11] = [
+    SAVE_STATE,
+    RESTORE_STATE,
+    CONCAT_MATRIX,
+    BEGIN_TEXT,
+    SET_TEXT_FONT,
+    SET_TEXT_MATRIX,
+    SET_HORIZONTAL_SCALING,
+    SET_TEXT_LEADING,
+    MOVE_TEXT,
+    MOVE_TEXT_SET_LEADING,
+    NEXT_LINE,
+];
+
+/// An affine transformation `[a b c d e f]` as used by PDF content streams.
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Returns the transformation which applies `m1` and then `m2`.
+fn multiply(m1: &Matrix, m2: &Matrix) -> Matrix {
+    [
+        m1[0] * m2[0] + m1[1] * m2[2],
+        m1[0] * m2[1] + m1[1] * m2[3],
+        m1[2] * m2[0] + m1[3] * m2[2],
+        m1[2] * m2[1] + m1[3] * m2[3],
+        m1[4] * m2[0] + m1[5] * m2[2] + m2[4],
+        m1[4] * m2[1] + m1[5] * m2[3] + m2[5],
+    ]
+}
+
+/// The parts of the graphics state that `q` and `Q` save and restore.
+#[derive(Clone, Debug)]
+struct SavedState {
+    ctm: Matrix,
+    /// The resource name of the font, once one is set
+    font_resource: Option<Vec<u8>>,
+    font_size: f32,
+    horizontal_scaling: f32,
+    leading: f32,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        SavedState {
+            ctm: IDENTITY,
+            font_resource: None,
+            font_size: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+        }
+    }
+}
+
+/// Tracks the graphics and text state of a content stream to determine the font
+/// text is shown in, the size it is rendered at and where.
+#[derive(Debug)]
+pub struct GraphicsState {
+    current: SavedState,
+    stack: Vec<SavedState>,
+    text_matrix: Matrix,
+    /// The text matrix at the start of the current line
+    text_line_matrix: Matrix,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        GraphicsState {
+            current: SavedState::default(),
+            stack: vec![],
+            text_matrix: IDENTITY,
+            text_line_matrix: IDENTITY,
+        }
+    }
+}
+
+impl GraphicsState {
+    /// Applies an operation from [`STATE_OPS`], or the line move of `'` and `"`.
+    /// Other operations are ignored.
+    pub fn update(&mut self, op: &Operation) -> Result<(), Error> {
+        match op.operator.as_str() {
+            SAVE_STATE => self.stack.push(self.current.clone()),
+            RESTORE_STATE => {
+                if let Some(state) = self.stack.pop() {
+                    self.current = state;
+                }
+            }
+            CONCAT_MATRIX => self.current.ctm = multiply(&read_matrix(op)?, &self.current.ctm),
+            BEGIN_TEXT => self.set_text_matrix(IDENTITY),
+            SET_TEXT_MATRIX => self.set_text_matrix(read_matrix(op)?),
+            SET_TEXT_LEADING => match &op.operands[..] {
+                [leading] => self.current.leading = leading.as_float()?,
+                _ => return Err(Error::Syntax(String::from("Invalid TL operands"))),
+            },
+            MOVE_TEXT | MOVE_TEXT_SET_LEADING => match &op.operands[..] {
+                [tx, ty] => {
+                    let (tx, ty) = (tx.as_float()?, ty.as_float()?);
+                    if op.operator == MOVE_TEXT_SET_LEADING {
+                        self.current.leading = -ty;
+                    }
+                    self.move_line(tx, ty);
+                }
+                _ => return Err(Error::Syntax(format!("Invalid {} operands", op.operator))),
+            },
+            NEXT_LINE | NEXT_LINE_SHOW_TEXT | NEXT_LINE_SHOW_SPACED_TEXT => {
+                self.move_line(0.0, -self.current.leading)
+            }
+            SET_TEXT_FONT => match &op.operands[..] {
+                [font, size] => {
+                    self.current.font_resource = Some(font.as_name()?.to_vec());
+                    self.current.font_size = size.as_float()?;
+                }
+                _ => return Err(Error::Syntax(String::from("Invalid Tf operands"))),
+            },
+            SET_HORIZONTAL_SCALING => match &op.operands[..] {
+                [scale] => self.current.horizontal_scaling = scale.as_float()? / 100.0,
+                _ => return Err(Error::Syntax(String::from("Invalid Tz operands"))),
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn set_text_matrix(&mut self, matrix: Matrix) {
+        self.text_matrix = matrix;
+        self.text_line_matrix = matrix;
+    }
+
+    fn move_line(&mut self, tx: f32, ty: f32) {
+        self.set_text_matrix(multiply(
+            &[1.0, 0.0, 0.0, 1.0, tx, ty],
+            &self.text_line_matrix,
+        ));
+    }
+
+    /// Returns the position of the start of the current line in default user space,
+    /// the coordinates used by destinations. Glyph advances are not tracked.
+    pub fn text_position(&self) -> (f32, f32) {
+        let m = multiply(&self.text_matrix, &self.current.ctm);
+        (m[4], m[5])
+    }
+
+    /// Returns the resource name of the current font, if one is set.
+    pub fn font_resource(&self) -> Option<&[u8]> {
+        self.current.font_resource.as_deref()
+    }
+
+    /// Returns the width and height text is rendered at, the font size
+    /// transformed by the text matrix and the current transformation matrix.
+    pub fn font_size(&self) -> (f32, f32) {
+        let m = multiply(&self.text_matrix, &self.current.ctm);
+        let size = self.current.font_size;
+        let width = size * self.current.horizontal_scaling * m[0].hypot(m[1]);
+        let height = size * m[2].hypot(m[3]);
+
+        // Rounded so that rounding errors in the matrices don't split a font in two
+        let round = |x: f32| (x * 100.0).round() / 100.0;
+        (round(width), round(height))
+    }
+}
+
+fn read_matrix(op: &Operation) -> Result<Matrix, Error> {
+    match &op.operands[..] {
+        [a, b, c, d, e, f] => Ok([
+            a.as_float()?,
+            b.as_float()?,
+            c.as_float()?,
+            d.as_float()?,
+            e.as_float()?,
+            f.as_float()?,
+        ]),
+        _ => Err(Error::Syntax(format!("Invalid {} operands", op.operator))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::Object;
+
+    use super::*;
+
+    /// Applies `(operator, operands)` in order to a new state.
+    fn state_after(ops: &[(&str, Vec<Object>)]) -> GraphicsState {
+        let mut state = GraphicsState::default();
+        for (operator, operands) in ops {
+            state
+                .update(&Operation::new(operator, operands.clone()))
+                .unwrap();
+        }
+        state
+    }
+
+    fn font(size: i64) -> (&'static str, Vec<Object>) {
+        (SET_TEXT_FONT, vec!["F1".into(), size.into()])
+    }
+
+    fn matrix(operator: &'static str, m: [f32; 6]) -> (&'static str, Vec<Object>) {
+        (operator, m.iter().map(|&x| x.into()).collect())
+    }
+
+    #[test]
+    fn font_size_is_scaled_by_the_text_matrix_and_ctm() {
+        let state = state_after(&[
+            matrix(CONCAT_MATRIX, [2.0, 0.0, 0.0, 2.0, 0.0, 0.0]),
+            (BEGIN_TEXT, vec![]),
+            font(10),
+            matrix(SET_TEXT_MATRIX, [1.5, 0.0, 0.0, 1.5, 10.0, 20.0]),
+        ]);
+        assert_eq!(state.font_resource(), Some(&b"F1"[..]));
+        assert_eq!(state.font_size(), (30.0, 30.0));
+        assert_eq!(state.text_position(), (20.0, 40.0));
+    }
+
+    #[test]
+    fn rotated_text_keeps_its_size() {
+        let state = state_after(&[
+            (BEGIN_TEXT, vec![]),
+            font(12),
+            matrix(SET_TEXT_MATRIX, [0.0, 1.0, -1.0, 0.0, 0.0, 0.0]),
+        ]);
+        assert_eq!(state.font_size(), (12.0, 12.0));
+    }
+
+    #[test]
+    fn restoring_the_state_undoes_changes_since_it_was_saved() {
+        let state = state_after(&[
+            font(12),
+            (SAVE_STATE, vec![]),
+            matrix(CONCAT_MATRIX, [2.0, 0.0, 0.0, 2.0, 0.0, 0.0]),
+            (SET_TEXT_FONT, vec!["F2".into(), 20.into()]),
+            (SET_HORIZONTAL_SCALING, vec![50.into()]),
+            (RESTORE_STATE, vec![]),
+            (BEGIN_TEXT, vec![]),
+        ]);
+        assert_eq!(state.font_resource(), Some(&b"F1"[..]));
+        assert_eq!(state.font_size(), (12.0, 12.0));
+        //restoring without a saved state is ignored
+        let state = state_after(&[font(12), (RESTORE_STATE, vec![])]);
+        assert_eq!(state.font_size(), (12.0, 12.0));
+    }
+
+    #[test]
+    fn matrices_are_concatenated_and_the_text_matrix_replaced() {
+        let state = state_after(&[
+            matrix(CONCAT_MATRIX, [1.0, 0.0, 0.0, 1.0, 100.0, 0.0]),
+            matrix(CONCAT_MATRIX, [2.0, 0.0, 0.0, 2.0, 0.0, 0.0]),
+            (BEGIN_TEXT, vec![]),
+            matrix(SET_TEXT_MATRIX, [1.0, 0.0, 0.0, 1.0, 50.0, 50.0]),
+            matrix(SET_TEXT_MATRIX, [1.0, 0.0, 0.0, 1.0, 10.0, 10.0]),
+            font(10),
+        ]);
+        assert_eq!(state.text_position(), (120.0, 20.0));
+        assert_eq!(state.font_size(), (20.0, 20.0));
+    }
+
+    #[test]
+    fn horizontal_scaling_narrows_the_font() {
+        let state = state_after(&[
+            (BEGIN_TEXT, vec![]),
+            font(10),
+            (SET_HORIZONTAL_SCALING, vec![50.into()]),
+        ]);
+        assert_eq!(state.font_size(), (5.0, 10.0));
+    }
+
+    #[test]
+    fn lines_move_by_the_offset_and_leading() {
+        let mut state = state_after(&[
+            (BEGIN_TEXT, vec![]),
+            (MOVE_TEXT, vec![72.into(), 700.into()]),
+            (MOVE_TEXT, vec![10.into(), (-5).into()]),
+        ]);
+        assert_eq!(state.text_position(), (82.0, 695.0));
+
+        //the leading is zero until it is set
+        state.update(&Operation::new(NEXT_LINE, vec![])).unwrap();
+        assert_eq!(state.text_position(), (82.0, 695.0));
+        state
+            .update(&Operation::new(SET_TEXT_LEADING, vec![14.into()]))
+            .unwrap();
+        state.update(&Operation::new(NEXT_LINE, vec![])).unwrap();
+        assert_eq!(state.text_position(), (82.0, 681.0));
+
+        //TD sets the leading to the negated vertical offset
+        let line_and_leading = Operation::new(MOVE_TEXT_SET_LEADING, vec![0.into(), (-20).into()]);
+        state.update(&line_and_leading).unwrap();
+        assert_eq!(state.text_position(), (82.0, 661.0));
+        let show_on_next_line = Operation::new(NEXT_LINE_SHOW_TEXT, vec!["Text".into()]);
+        state.update(&show_on_next_line).unwrap();
+        assert_eq!(state.text_position(), (82.0, 641.0));
+
+        //a new text object starts at the origin again
+        state.update(&Operation::new(BEGIN_TEXT, vec![])).unwrap();
+        assert_eq!(state.text_position(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn invalid_operands_are_errors() {
+        let mut state = GraphicsState::default();
+        assert!(state
+            .update(&Operation::new(SET_TEXT_FONT, vec!["F1".into()]))
+            .is_err());
+        assert!(state
+            .update(&Operation::new(SET_TEXT_MATRIX, vec![1.into()]))
+            .is_err());
+        assert!(state
+            .update(&Operation::new(MOVE_TEXT, vec!["x".into(), 0.into()]))
+            .is_err());
+    }
+}
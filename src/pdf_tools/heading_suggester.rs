@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+
+use lopdf::Document;
+
+use super::{
+    error::PdfError,
+    pdf_font::PdfFont,
+    text_index::{PageText, PdfTextIndex, RunBreak},
+};
+
+/// How likely a font is to be used for headings, and the outline level it would get.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadingSuggestion {
+    /// Zero-based depth in the outline, `None` if the font isn't suggested as a heading font
+    pub level: Option<usize>,
+    /// Between 0 and 1
+    pub confidence: f32,
+}
+
+pub trait PdfHeadingSuggester {
+    /// Scores every font that shows text in the document, and suggests the fonts
+    /// most likely to be heading fonts for up to `max_levels` outline levels.
+    fn suggest_heading_fonts(
+        &self,
+        max_levels: usize,
+    ) -> Result<BTreeMap<PdfFont, HeadingSuggestion>, PdfError>;
+}
+
+/// Fonts scoring at least this are suggested as heading fonts.
+const SUGGESTION_THRESHOLD: f32 = 0.5;
+
+/// Words in font names of bold and heavier weights, in lowercase.
+const BOLD_NAMES: [&str; 4] = ["bold", "black", "heavy", "demi"];
+
+#[derive(Debug, Default)]
+struct FontUsage {
+    /// Number of non-whitespace characters shown in the font
+    glyphs: usize,
+    /// Number of lines containing text in the font
+    lines: usize,
+    /// Number of those lines containing no other font
+    standalone_lines: usize,
+    /// Characters on those lines in any font
+    line_glyphs: usize,
+    /// Number of those lines in the top third of their page
+    top_lines: usize,
+}
+
+/// A line of text, as the fonts used on it with the characters shown in each.
+#[derive(Default)]
+struct Line {
+    segments: Vec<(PdfFont, usize)>,
+    /// Height on the page between 0 at the bottom and 1 at the top
+    height: f32,
+}
+
+impl PdfHeadingSuggester for Document {
+    fn suggest_heading_fonts(
+        &self,
+        max_levels: usize,
+    ) -> Result<BTreeMap<PdfFont, HeadingSuggestion>, PdfError> {
+        PdfTextIndex::new(self).suggest_heading_fonts(max_levels)
+    }
+}
+
+impl PdfHeadingSuggester for PdfTextIndex {
+    fn suggest_heading_fonts(
+        &self,
+        max_levels: usize,
+    ) -> Result<BTreeMap<PdfFont, HeadingSuggestion>, PdfError> {
+        let mut usage = BTreeMap::<PdfFont, FontUsage>::new();
+        for page in &self.pages {
+            for line in get_lines_on_page(page) {
+                let line_glyphs: usize = line.segments.iter().map(|(_, glyphs)| glyphs).sum();
+                let standalone = line
+                    .segments
+                    .iter()
+                    .all(|(font, _)| *font == line.segments[0].0);
+                let mut counted = vec![];
+                for (font, glyphs) in line.segments {
+                    let font_usage = usage.entry(font.clone()).or_default();
+                    font_usage.glyphs += glyphs;
+                    if counted.contains(&font) {
+                        continue;
+                    }
+                    font_usage.lines += 1;
+                    font_usage.line_glyphs += line_glyphs;
+                    if standalone {
+                        font_usage.standalone_lines += 1;
+                    }
+                    if line.height > 2.0 / 3.0 {
+                        font_usage.top_lines += 1;
+                    }
+                    counted.push(font);
+                }
+            }
+        }
+        Ok(suggest_levels(&usage, max_levels))
+    }
+}
+
+fn suggest_levels(
+    usage: &BTreeMap<PdfFont, FontUsage>,
+    max_levels: usize,
+) -> BTreeMap<PdfFont, HeadingSuggestion> {
+    let total_glyphs = usage
+        .values()
+        .map(|usage| usage.glyphs)
+        .sum::<usize>()
+        .max(1);
+    //the font most of the text is in is assumed to be the body text
+    let Some((body_font, _)) = usage.iter().max_by_key(|(_, usage)| usage.glyphs) else {
+        return BTreeMap::new();
+    };
+    let body_size = body_font.size.1.max(f32::EPSILON);
+
+    let mut suggestions: BTreeMap<PdfFont, HeadingSuggestion> = usage
+        .iter()
+        .map(|(font, usage)| {
+            let confidence = if font == body_font {
+                0.0
+            } else {
+                heading_confidence(font, usage, body_size, total_glyphs)
+            };
+            let suggestion = HeadingSuggestion {
+                level: None,
+                confidence,
+            };
+            (font.clone(), suggestion)
+        })
+        .collect();
+
+    //larger fonts are higher levels, fonts of the same size share a level
+    let mut heading_sizes: Vec<f32> = suggestions
+        .iter()
+        .filter(|(_, suggestion)| suggestion.confidence >= SUGGESTION_THRESHOLD)
+        .map(|(font, _)| font.size.1)
+        .collect();
+    heading_sizes.sort_by(|a, b| b.total_cmp(a));
+    heading_sizes.dedup();
+    for (font, suggestion) in &mut suggestions {
+        if suggestion.confidence >= SUGGESTION_THRESHOLD {
+            suggestion.level = heading_sizes
+                .iter()
+                .position(|&size| size == font.size.1)
+                .filter(|&level| level < max_levels);
+        }
+    }
+    suggestions
+}
+
+/// Scores a font by how much larger than the body text it is, how little text is in it,
+/// whether it's bold, how short its lines are and how often they are at the top of a page.
+/// Headings are on lines of their own, so the score is scaled by how often that is the case.
+fn heading_confidence(
+    font: &PdfFont,
+    usage: &FontUsage,
+    body_size: f32,
+    total_glyphs: usize,
+) -> f32 {
+    let lines = usage.lines.max(1) as f32;
+    let relative_size = font.size.1 / body_size;
+    let is_bold = BOLD_NAMES
+        .iter()
+        .any(|name| font.base_font.to_lowercase().contains(name));
+    //text the same size as the body can only stand out by being bold
+    if relative_size < 1.0 || relative_size < 1.05 && !is_bold {
+        return 0.0;
+    }
+
+    //1.5 times the body size or larger scores fully
+    let size = ((relative_size - 1.0) / 0.5).clamp(0.0, 1.0);
+    //a tenth of the text or more scores nothing
+    let rarity = 1.0 - (usage.glyphs as f32 / total_glyphs as f32 * 10.0).min(1.0);
+    let bold = if is_bold { 1.0 } else { 0.0 };
+    //lines of 40 characters or fewer score fully, lines of 100 or more nothing
+    let average_line = usage.line_glyphs as f32 / lines;
+    let short_lines = ((100.0 - average_line) / 60.0).clamp(0.0, 1.0);
+    let top_of_page = usage.top_lines as f32 / lines;
+    let standalone = usage.standalone_lines as f32 / lines;
+
+    let score = 0.35 * size + 0.2 * rarity + 0.15 * bold + 0.15 * short_lines + 0.15 * top_of_page;
+    score * standalone
+}
+
+/// Splits the text on a page into lines. A line ends at the end of a text object,
+/// at a new text matrix, or when moving to the next line.
+fn get_lines_on_page(page: &PageText) -> Vec<Line> {
+    let (bottom, top) = page.vertical_bounds;
+    let mut lines = vec![];
+    let mut line = Line::default();
+    let mut end_line = |line: &mut Line| {
+        let line = std::mem::take(line);
+        if !line.segments.is_empty() {
+            lines.push(line);
+        }
+    };
+
+    for run in &page.runs {
+        if run.run_break != RunBreak::FontChange {
+            end_line(&mut line);
+        }
+        if run.font.base_font.is_empty() {
+            continue;
+        }
+        let glyphs = run.text.chars().filter(|c| !c.is_whitespace()).count();
+        if glyphs == 0 {
+            continue;
+        }
+
+        if line.segments.is_empty() {
+            let (_, baseline) = run.position;
+            line.height = (baseline - bottom) / (top - bottom).max(1.0);
+        }
+        match line.segments.last_mut() {
+            Some((last_font, count)) if *last_font == run.font => *count += glyphs,
+            _ => line.segments.push((run.font.clone(), glyphs)),
+        }
+    }
+    end_line(&mut line);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_tools::text_index::TextRun;
+
+    const BODY_LINE: &str =
+        "Body text fills most of the page, in lines of about seventy characters.";
+
+    fn run(font: &PdfFont, text: &str, top: f32, run_break: RunBreak) -> TextRun {
+        TextRun {
+            font: font.clone(),
+            text: String::from(text),
+            position: (72.0, top),
+            run_break,
+        }
+    }
+
+    /// Pages starting with a heading, followed by body text with a bold word in it.
+    fn text_index(heading: &PdfFont, body: &PdfFont, bold: &PdfFont) -> PdfTextIndex {
+        let pages = (1..=5)
+            .map(|page_number| {
+                let mut runs = vec![run(heading, "Chapter", 720.0, RunBreak::TextObject)];
+                for line in 0..20 {
+                    let top = 680.0 - 14.0 * line as f32;
+                    runs.push(run(body, BODY_LINE, top, RunBreak::Line));
+                    runs.push(run(bold, "Note", top, RunBreak::FontChange));
+                    runs.push(run(body, BODY_LINE, top, RunBreak::FontChange));
+                }
+                PageText {
+                    page_number,
+                    vertical_bounds: (0.0, 792.0),
+                    runs,
+                    problems: vec![],
+                }
+            })
+            .collect();
+        PdfTextIndex { pages }
+    }
+
+    #[test]
+    fn large_bold_standalone_font_is_the_top_level() {
+        let heading = PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0));
+        let body = PdfFont::new(String::from("Helvetica"), (10.0, 10.0));
+        let bold = PdfFont::new(String::from("Helvetica-Bold"), (10.0, 10.0));
+        let suggestions = text_index(&heading, &body, &bold)
+            .suggest_heading_fonts(3)
+            .unwrap();
+
+        assert_eq!(suggestions.len(), 3);
+        assert_eq!(suggestions[&heading].level, Some(0));
+        assert!(suggestions[&heading].confidence >= SUGGESTION_THRESHOLD);
+        assert_eq!(
+            suggestions[&body],
+            HeadingSuggestion {
+                level: None,
+                confidence: 0.0,
+            }
+        );
+        //bold words within body text are not on lines of their own
+        assert_eq!(suggestions[&bold].level, None);
+    }
+
+    #[test]
+    fn levels_beyond_the_maximum_are_not_suggested() {
+        let heading = PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0));
+        let body = PdfFont::new(String::from("Helvetica"), (10.0, 10.0));
+        let bold = PdfFont::new(String::from("Helvetica-Bold"), (10.0, 10.0));
+        let suggestions = text_index(&heading, &body, &bold)
+            .suggest_heading_fonts(0)
+            .unwrap();
+        assert!(suggestions
+            .values()
+            .all(|suggestion| suggestion.level.is_none()));
+    }
+
+    #[test]
+    fn documents_without_text_have_no_suggestions() {
+        let suggestions = PdfTextIndex::default().suggest_heading_fonts(3).unwrap();
+        assert!(suggestions.is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+use lopdf::{Document, Object};
+
+use super::error::PdfError;
+
+/// Every PDF starts with this, followed by its version.
+const PDF_HEADER: &[u8] = b"%PDF-";
+
+/// Returns the version in a PDF's header, or `None` if the bytes don't start like a PDF.
+pub fn pdf_version(bytes: &[u8]) -> Option<String> {
+    let rest = bytes.strip_prefix(PDF_HEADER)?;
+    let version = rest
+        .iter()
+        .take_while(|byte| !byte.is_ascii_whitespace() && **byte != b'%')
+        .map(|&byte| char::from(byte))
+        .collect();
+    Some(version)
+}
+
+/// Versions 1.0 to 1.7, and 2.0, are the ones published so far.
+fn is_supported_version(version: &str) -> bool {
+    let number = version
+        .split_once('.')
+        .and_then(|(major, minor)| Some((major.parse::<u32>().ok()?, minor.parse::<u32>().ok()?)));
+    matches!(number, Some((1, 0..=7)) | Some((2, 0)))
+}
+
+/// What the owner of an encrypted PDF can forbid, by the bit of its `P` entry, counting from 1.
+const PERMISSIONS: [(u32, &str); 6] = [
+    (3, "printing"),
+    (4, "modifying"),
+    (5, "copying"),
+    (6, "annotating"),
+    (9, "filling in forms"),
+    (11, "assembling"),
+];
+
+/// Returns what a PDF's owner forbids, given the `P` entry of its encryption dictionary.
+fn restrictions(permissions: i64) -> Vec<&'static str> {
+    PERMISSIONS
+        .iter()
+        .filter(|(bit, _)| permissions & 1 << (bit - 1) == 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Parses a PDF from memory.
+///
+/// Documents encrypted with an empty user password only to mark them as unrestricted
+/// are decrypted. Copies are saved without encryption, so documents whose owner
+/// restricts what can be done with them are refused, like those needing a password.
+pub fn load_document(bytes: &[u8]) -> Result<Document, PdfError> {
+    let version = pdf_version(bytes).ok_or(PdfError::NotPdf)?;
+    if !is_supported_version(&version) {
+        return Err(PdfError::UnsupportedVersion(version));
+    }
+    let mut doc = Document::load_mem(bytes).map_err(PdfError::Load)?;
+    if !doc.is_encrypted() {
+        return Ok(doc);
+    }
+    let permissions = doc
+        .get_encrypted()
+        .and_then(|encrypt| encrypt.get(b"P"))
+        .and_then(Object::as_i64);
+    if doc.decrypt("").is_err() {
+        return Err(PdfError::Encrypted);
+    }
+    let restrictions = permissions.map(restrictions).unwrap_or_default();
+    if !restrictions.is_empty() {
+        return Err(PdfError::Restricted(restrictions));
+    }
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restrictions_are_the_cleared_permission_bits() {
+        //every bit set but those of printing and copying
+        let permissions = !(1 << 2 | 1 << 4);
+        assert_eq!(restrictions(permissions), ["printing", "copying"]);
+        assert!(restrictions(-1).is_empty());
+    }
+}
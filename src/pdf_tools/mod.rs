@@ -1,10 +1,25 @@
 pub mod pdf_font;
 pub mod pdf_outline;
 
+mod error;
+mod font_decoder;
 mod font_reader;
+mod graphics_state;
+mod heading_suggester;
+mod loader;
 mod outline_generator;
 mod outline_inserter;
+mod outline_interchange;
+mod outline_reader;
+mod page_fonts;
+mod text_index;
 
+pub use error::PdfError;
+pub use font_decoder::FontDecoders;
 pub use font_reader::PdfFontReader;
-pub use outline_generator::PdfOutlineGenerator;
-pub use outline_inserter::PdfOutlineInserter;
+pub use heading_suggester::{HeadingSuggestion, PdfHeadingSuggester};
+pub use loader::{load_document, pdf_version};
+pub use outline_generator::{add_page_headings, PdfOutlineGenerator};
+pub use outline_inserter::{InsertMode, PdfOutlineInserter};
+pub use outline_reader::PdfOutlineReader;
+pub use text_index::{read_pages, PageText, PdfTextIndex, RunBreak, TextRun};
@@ -1,78 +1,185 @@
-use lopdf::{Document, ObjectId};
+use lopdf::Document;
 
 use super::{
-    font_reader::{update_font_from_operation, DISPLAY_TEXT_OPS, SET_TEXT_FONT, SET_TEXT_MATRIX},
+    error::PdfError,
     pdf_font::PdfFont,
     pdf_outline::{PdfOutline, PdfOutlineEntry},
+    text_index::{PageText, PdfTextIndex, RunBreak},
 };
 
 pub trait PdfOutlineGenerator {
-    fn generate_outline(&self, fonts: &[Vec<PdfFont>]) -> PdfOutline;
+    fn generate_outline(&self, fonts: &[Vec<PdfFont>]) -> Result<PdfOutline, PdfError>;
 }
 
 impl PdfOutlineGenerator for Document {
-    fn generate_outline(&self, heading_fonts: &[Vec<PdfFont>]) -> PdfOutline {
+    fn generate_outline(&self, heading_fonts: &[Vec<PdfFont>]) -> Result<PdfOutline, PdfError> {
+        PdfTextIndex::new(self).generate_outline(heading_fonts)
+    }
+}
+
+impl PdfOutlineGenerator for PdfTextIndex {
+    fn generate_outline(&self, heading_fonts: &[Vec<PdfFont>]) -> Result<PdfOutline, PdfError> {
         let mut outline = PdfOutline::new();
-        const MAX_DEPTH: usize = 3;
-        for (current_depth, fonts) in heading_fonts.iter().enumerate() {
-            if current_depth >= MAX_DEPTH {
-                break;
-            }
-            'page_loop: for (page_number, page_id) in self.get_pages() {
-                for font in fonts.iter() {
-                    if let Some(title) = get_first_instance_on_page(self, page_id, font) {
-                        let mut parent = &mut outline;
-                        for _depth in 0..current_depth {
-                            if let Some(entry) = parent
-                                .iter_mut()
-                                .take_while(|entry| entry.page_number <= page_number)
-                                .last()
-                            {
-                                parent = &mut entry.children;
-                            } else {
-                                continue 'page_loop;
-                            }
-                        }
-
-                        parent.push(PdfOutlineEntry::new(page_number, title));
-                    }
-                }
+        for page in &self.pages {
+            add_page_headings(&mut outline, page, heading_fonts);
+        }
+        Ok(outline)
+    }
+}
+
+/// Adds the headings on a page to an outline of the pages before it.
+pub fn add_page_headings(
+    outline: &mut PdfOutline,
+    page: &PageText,
+    heading_fonts: &[Vec<PdfFont>],
+) {
+    'heading_loop: for heading in get_headings_on_page(page, heading_fonts) {
+        //headings are nested under the most recent heading one level up,
+        //and dropped if there is none
+        let mut parent = &mut *outline;
+        for _depth in 0..heading.depth {
+            match parent.last_mut() {
+                Some(entry) => parent = &mut entry.children,
+                None => continue 'heading_loop,
             }
         }
-        outline
+
+        let mut entry = PdfOutlineEntry::new(page.page_number, heading.title);
+        entry.position = Some(heading.position);
+        parent.push(entry);
     }
 }
 
-fn get_first_instance_on_page(doc: &Document, page_id: ObjectId, font: &PdfFont) -> Option<String> {
-    let mut first_instance = String::default();
-    let mut current_font = PdfFont::default();
-
-    let contents = doc.get_and_decode_page_content(page_id).ok()?;
-    for op in contents.operations {
-        match op.operator.as_str() {
-            SET_TEXT_MATRIX | SET_TEXT_FONT => {
-                if first_instance.is_empty() {
-                    update_font_from_operation(doc, &mut current_font, op, page_id).ok()?
-                } else {
-                    return Some(first_instance);
-                }
+struct Heading {
+    depth: usize,
+    title: String,
+    /// The left and top of the first line of the heading
+    position: (f32, f32),
+}
+
+/// Returns every run of text in one of the heading fonts, in content stream order.
+///
+/// A heading ends at the end of its text object, at a new text matrix,
+/// or when the text is shown in a font of a different level. Its lines
+/// are joined with a space, and runs of whitespace in it collapsed.
+fn get_headings_on_page(page: &PageText, heading_fonts: &[Vec<PdfFont>]) -> Vec<Heading> {
+    let mut headings = vec![];
+    let mut current_heading: Option<Heading> = None;
+
+    let mut end_heading = |current_heading: &mut Option<Heading>| {
+        if let Some(mut heading) = current_heading.take() {
+            heading.title = heading
+                .title
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !heading.title.is_empty() {
+                headings.push(heading);
             }
-            x if current_font == *font && DISPLAY_TEXT_OPS.contains(&x) => {
-                if let Some(string_object) = match x {
-                    "Tj" | "'" => op.operands.get(0),
-                    "\"" => op.operands.get(2),
-                    "TJ" => op.operands.get(0)?.as_array().ok()?.get(0),
-                    _ => unreachable!(),
-                } {
-                    first_instance.push_str(&string_object.as_string().ok()?);
-                }
+        }
+    };
+
+    for run in &page.runs {
+        let depth = heading_fonts
+            .iter()
+            .position(|fonts| fonts.contains(&run.font));
+        if run.run_break == RunBreak::TextObject
+            || current_heading.as_ref().map(|heading| heading.depth) != depth
+        {
+            end_heading(&mut current_heading);
+        }
+        let Some(depth) = depth else {
+            continue;
+        };
+
+        let heading = current_heading.get_or_insert_with(|| {
+            let (left, baseline) = run.position;
+            Heading {
+                depth,
+                title: String::new(),
+                position: (left, baseline + run.font.size.1),
             }
-            _ => (),
+        });
+        if run.run_break == RunBreak::Line {
+            heading.title.push(' ');
+        }
+        heading.title.push_str(&run.text);
+    }
+    end_heading(&mut current_heading);
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_tools::text_index::TextRun;
+
+    fn page(runs: Vec<(&PdfFont, &str, RunBreak)>) -> PageText {
+        let runs = runs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (font, text, run_break))| TextRun {
+                font: font.clone(),
+                text: String::from(text),
+                position: (72.0, 720.0 - 20.0 * i as f32),
+                run_break,
+            })
+            .collect();
+        PageText {
+            page_number: 1,
+            vertical_bounds: (0.0, 792.0),
+            runs,
+            problems: vec![],
         }
     }
-    if first_instance.is_empty() {
-        None
-    } else {
-        Some(first_instance)
+
+    fn titles(outline: &PdfOutline) -> Vec<&str> {
+        outline.iter().map(|entry| entry.title.as_str()).collect()
+    }
+
+    #[test]
+    fn heading_lines_are_joined_with_a_space() {
+        let heading = PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0));
+        let page = page(vec![
+            (&heading, "Getting", RunBreak::TextObject),
+            (&heading, "Started", RunBreak::Line),
+        ]);
+        let outline = PdfTextIndex { pages: vec![page] }
+            .generate_outline(&[vec![heading]])
+            .unwrap();
+        assert_eq!(titles(&outline), ["Getting Started"]);
+        assert_eq!(outline[0].position, Some((72.0, 738.0)));
+    }
+
+    #[test]
+    fn whitespace_in_headings_is_collapsed() {
+        let heading = PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0));
+        let page = page(vec![
+            (&heading, " 1.2 \t Installing ", RunBreak::TextObject),
+            (&heading, "  from  source", RunBreak::Line),
+            (&heading, "  ", RunBreak::TextObject),
+        ]);
+        let outline = PdfTextIndex { pages: vec![page] }
+            .generate_outline(&[vec![heading]])
+            .unwrap();
+        assert_eq!(titles(&outline), ["1.2 Installing from source"]);
+    }
+
+    #[test]
+    fn headings_end_at_other_fonts_and_text_objects() {
+        let chapter = PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0));
+        let section = PdfFont::new(String::from("Helvetica-Bold"), (14.0, 14.0));
+        let body = PdfFont::new(String::from("Helvetica"), (10.0, 10.0));
+        let page = page(vec![
+            (&chapter, "Usage", RunBreak::TextObject),
+            (&body, "Body text", RunBreak::Line),
+            (&section, "Options", RunBreak::Line),
+            (&section, "Examples", RunBreak::TextObject),
+        ]);
+        let outline = PdfTextIndex { pages: vec![page] }
+            .generate_outline(&[vec![chapter], vec![section]])
+            .unwrap();
+        assert_eq!(titles(&outline), ["Usage"]);
+        assert_eq!(titles(&outline[0].children), ["Options", "Examples"]);
     }
 }
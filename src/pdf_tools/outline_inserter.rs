@@ -1,13 +1,20 @@
-use lopdf::{dictionary, Document, Error, Object, ObjectId};
+use std::collections::BTreeMap;
 
-use super::pdf_outline::{PdfOutline, PdfOutlineEntry};
+use lopdf::{dictionary, Document, Error, Object, ObjectId, StringFormat};
+
+use super::{
+    error::PdfError,
+    outline_reader::{destination_page, first_child, item_destination, outline_items},
+    pdf_outline::{PdfOutline, PdfOutlineEntry},
+};
 
 trait PdfOutlineEntryInserter {
     fn insert_outline_entries(
         &mut self,
         parent_id: ObjectId,
         children: &[PdfOutlineEntry],
-    ) -> (ObjectId, ObjectId, i32);
+        pages: &BTreeMap<u32, ObjectId>,
+    ) -> Result<Vec<ObjectId>, PdfError>;
 }
 
 impl PdfOutlineEntryInserter for Document {
@@ -15,70 +22,265 @@ impl PdfOutlineEntryInserter for Document {
         &mut self,
         parent_id: ObjectId,
         children: &[PdfOutlineEntry],
-    ) -> (ObjectId, ObjectId, i32) {
+        pages: &BTreeMap<u32, ObjectId>,
+    ) -> Result<Vec<ObjectId>, PdfError> {
         let mut entry_ids = vec![];
 
         for entry in children {
+            //destinations in the same document must reference the page object
+            let page_id = *pages
+                .get(&entry.page_number)
+                .ok_or(Error::PageNumberNotFound(entry.page_number))?;
+
+            //scroll to the heading itself when its position is known, keeping the zoom level
+            let (left, top) = match entry.position {
+                Some((left, top)) => (left.into(), top.into()),
+                None => (Object::Null, Object::Null),
+            };
             let entry_id = self.add_object(dictionary!(
-                "Title" => Object::string_literal(entry.title.clone()),
+                "Title" => encode_text_string(&entry.title),
                 "Parent" => parent_id,
-                "Dest" => vec![ (entry.page_number-1).into(), "XYZ".into(), Object::Null, Object::Null, Object::Null,],
+                "Dest" => vec![ page_id.into(), "XYZ".into(), left, top, Object::Null,],
             ));
 
             entry_ids.push(entry_id);
 
             if !entry.children.is_empty() {
-                let (first, last, count) = self.insert_outline_entries(entry_id, &entry.children);
-                let entry = self.get_dictionary_mut(entry_id).unwrap();
-
-                entry.set("First", first);
-                entry.set("Last", last);
-                entry.set("Count", -count);
+                let child_ids = self.insert_outline_entries(entry_id, &entry.children, pages)?;
+                //negative count to start collapsed
+                self.get_dictionary_mut(entry_id)?
+                    .set("Count", -(child_ids.len() as i64));
             }
         }
 
-        for i in 0..entry_ids.len() {
-            if i != 0 {
-                self.get_dictionary_mut(entry_ids[i])
-                    .unwrap()
-                    .set("Prev", entry_ids[i - 1]);
-            }
-            if i != entry_ids.len() - 1 {
-                self.get_dictionary_mut(entry_ids[i])
-                    .unwrap()
-                    .set("Next", entry_ids[i + 1]);
-            }
-        }
+        link_siblings(self, parent_id, &entry_ids)?;
+        Ok(entry_ids)
+    }
+}
+
+/// What happens to an outline the document already has when a new one is inserted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InsertMode {
+    /// The existing outline is removed from the document
+    #[default]
+    Replace,
+    /// The new top-level entries follow the existing ones
+    Append,
+    /// Top-level entries of both outlines are interleaved by page number
+    Merge,
+}
 
-        (
-            entry_ids[0],
-            entry_ids[entry_ids.len() - 1],
-            entry_ids.len() as i32,
-        )
+impl std::str::FromStr for InsertMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "replace" => Ok(InsertMode::Replace),
+            "append" => Ok(InsertMode::Append),
+            "merge" => Ok(InsertMode::Merge),
+            _ => Err(format!(
+                "unknown insert mode '{}', expected replace, append or merge",
+                s
+            )),
+        }
     }
 }
 
 pub trait PdfOutlineInserter {
-    fn insert_outline(&mut self, outline: &PdfOutline) -> Result<(), Error>;
+    fn insert_outline(&mut self, outline: &PdfOutline, mode: InsertMode) -> Result<(), PdfError>;
 }
 
 impl PdfOutlineInserter for Document {
-    fn insert_outline(&mut self, outline: &PdfOutline) -> Result<(), Error> {
-        let outlines_id = self.add_object(dictionary! {
-            "Type" => "Outlines",
-        });
-
-        if !outline.is_empty() {
-            let (first, last, _) = self.insert_outline_entries(outlines_id, outline);
-            self.get_dictionary_mut(outlines_id)
-                .unwrap()
-                .set("First", first);
-            self.get_dictionary_mut(outlines_id)
-                .unwrap()
-                .set("Last", last);
+    fn insert_outline(&mut self, outline: &PdfOutline, mode: InsertMode) -> Result<(), PdfError> {
+        let pages = self.get_pages();
+        //checked up front, so that a failed insert leaves the document as it was
+        if let Some(page_number) = missing_page(outline, &pages) {
+            return Err(Error::PageNumberNotFound(page_number).into());
+        }
+
+        let existing_id = self
+            .catalog()?
+            .get(b"Outlines")
+            .and_then(Object::as_reference)
+            .ok()
+            .filter(|id| self.get_dictionary(*id).is_ok());
+
+        let outlines_id = match existing_id {
+            Some(id) if mode != InsertMode::Replace => id,
+            _ => {
+                if let Some(id) = existing_id {
+                    remove_outline(self, id);
+                }
+                self.add_object(dictionary! {
+                    "Type" => "Outlines",
+                })
+            }
+        };
+        if outline.is_empty() {
+            self.catalog_mut()?.set("Outlines", outlines_id);
+            return Ok(());
         }
 
+        let existing_items = first_child(self, outlines_id)
+            .map(|first| outline_items(self, first))
+            .unwrap_or_default();
+        let new_items = self.insert_outline_entries(outlines_id, outline, &pages)?;
+
+        let items = match mode {
+            InsertMode::Replace | InsertMode::Append => [existing_items, new_items].concat(),
+            InsertMode::Merge => {
+                let page_numbers = pages.iter().map(|(&number, &id)| (id, number)).collect();
+                let existing_pages = item_pages(self, &existing_items, &page_numbers);
+                let mut merged = Vec::with_capacity(existing_items.len() + new_items.len());
+                let mut existing = existing_items.iter().zip(existing_pages).peekable();
+                for (new_item, entry) in new_items.iter().zip(outline) {
+                    //existing entries come first on the same page
+                    while let Some((&item, _)) =
+                        existing.next_if(|(_, page)| *page <= entry.page_number)
+                    {
+                        merged.push(item);
+                    }
+                    merged.push(*new_item);
+                }
+                merged.extend(existing.map(|(&item, _)| item));
+                merged
+            }
+        };
+        link_siblings(self, outlines_id, &items)?;
+
+        //the root's count is the number of visible entries, including open descendants
+        let visible = items
+            .iter()
+            .map(|&id| {
+                let count = self
+                    .get_dictionary(id)
+                    .and_then(|item| item.get(b"Count"))
+                    .and_then(Object::as_i64)
+                    .unwrap_or(0);
+                1 + count.max(0)
+            })
+            .sum::<i64>();
+        self.get_dictionary_mut(outlines_id)?.set("Count", visible);
+
         self.catalog_mut()?.set("Outlines", outlines_id);
         Ok(())
     }
 }
+
+/// Returns the first page number of an entry that isn't a page of the document.
+fn missing_page(entries: &[PdfOutlineEntry], pages: &BTreeMap<u32, ObjectId>) -> Option<u32> {
+    entries.iter().find_map(|entry| {
+        if pages.contains_key(&entry.page_number) {
+            missing_page(&entry.children, pages)
+        } else {
+            Some(entry.page_number)
+        }
+    })
+}
+
+/// Encodes a PDF text string. ASCII is written as is, everything else as UTF-16BE
+/// with a byte order mark, since viewers read other bytes as PDFDocEncoding.
+fn encode_text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// Makes `items` the children of `parent_id`, in order.
+fn link_siblings(doc: &mut Document, parent_id: ObjectId, items: &[ObjectId]) -> Result<(), Error> {
+    for (i, &id) in items.iter().enumerate() {
+        let item = doc.get_dictionary_mut(id)?;
+        item.set("Parent", parent_id);
+        match i.checked_sub(1) {
+            Some(prev) => item.set("Prev", items[prev]),
+            None => {
+                item.remove(b"Prev");
+            }
+        }
+        match items.get(i + 1) {
+            Some(&next) => item.set("Next", next),
+            None => {
+                item.remove(b"Next");
+            }
+        }
+    }
+
+    let parent = doc.get_dictionary_mut(parent_id)?;
+    match (items.first(), items.last()) {
+        (Some(&first), Some(&last)) => {
+            parent.set("First", first);
+            parent.set("Last", last);
+        }
+        _ => {
+            parent.remove(b"First");
+            parent.remove(b"Last");
+        }
+    }
+    Ok(())
+}
+
+/// Returns the page number each outline item points to. Items whose destination
+/// cannot be resolved are treated as being on the page of the item before them.
+fn item_pages(
+    doc: &Document,
+    items: &[ObjectId],
+    page_numbers: &BTreeMap<ObjectId, u32>,
+) -> Vec<u32> {
+    let mut previous_page = 0;
+    items
+        .iter()
+        .map(|&id| {
+            let page = doc
+                .get_dictionary(id)
+                .ok()
+                .and_then(|item| item_destination(doc, item))
+                .and_then(|destination| destination_page(destination, page_numbers))
+                .unwrap_or(previous_page);
+            previous_page = page;
+            page
+        })
+        .collect()
+}
+
+/// Removes an outline and every item in it from the document.
+fn remove_outline(doc: &mut Document, outlines_id: ObjectId) {
+    let mut pending = vec![outlines_id];
+    while let Some(id) = pending.pop() {
+        let Some(Object::Dictionary(item)) = doc.objects.remove(&id) else {
+            continue;
+        };
+        if let Ok(first) = item.get(b"First").and_then(Object::as_reference) {
+            pending.extend(outline_items(doc, first));
+        }
+        //actions are the only other objects owned by an outline item
+        if let Ok(action_id) = item.get(b"A").and_then(Object::as_reference) {
+            doc.objects.remove(&action_id);
+        }
+    }
+    if let Ok(catalog) = doc.catalog_mut() {
+        catalog.remove(b"Outlines");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_titles_are_written_as_is() {
+        let title = encode_text_string("Chapter 1");
+        assert_eq!(title.as_str().unwrap(), b"Chapter 1");
+    }
+
+    #[test]
+    fn other_titles_are_written_as_utf16() {
+        let title = encode_text_string("Café");
+        assert_eq!(
+            title.as_str().unwrap(),
+            &[0xFE, 0xFF, 0, b'C', 0, b'a', 0, b'f', 0, 0xE9]
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+
+use super::pdf_outline::{PdfOutline, PdfOutlineEntry};
+
+/// Writes one `BookmarkBegin` record per entry, as `pdftk dump_data` does
+/// and `pdftk update_info` reads.
+pub fn outline_to_pdftk(outline: &PdfOutline) -> String {
+    let mut text = String::new();
+    recursive_outline_to_pdftk(outline, 1, &mut text);
+    text
+}
+
+fn recursive_outline_to_pdftk(outline: &PdfOutline, level: usize, text: &mut String) {
+    for entry in outline {
+        text.push_str(&format!(
+            "BookmarkBegin\nBookmarkTitle: {}\nBookmarkLevel: {}\nBookmarkPageNumber: {}\n",
+            encode_pdftk_entities(&entry.title),
+            level,
+            entry.page_number
+        ));
+        recursive_outline_to_pdftk(&entry.children, level + 1, text);
+    }
+}
+
+#[derive(Default)]
+struct PdftkBookmark {
+    line_number: usize,
+    title: Option<String>,
+    level: Option<usize>,
+    page_number: Option<u32>,
+}
+
+/// Reads the bookmark records of `pdftk dump_data` or `dump_data_utf8` output, ignoring
+/// everything else in it. Errors include the line they occurred on.
+pub fn outline_from_pdftk(text: &str) -> Result<PdfOutline, String> {
+    let mut bookmarks = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim_end_matches('\r');
+        if line == "BookmarkBegin" {
+            bookmarks.push(PdftkBookmark {
+                line_number,
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let Some(bookmark) = bookmarks.last_mut() else {
+            continue;
+        };
+        match key {
+            "BookmarkTitle" => bookmark.title = Some(decode_pdftk_entities(value)),
+            "BookmarkLevel" => {
+                bookmark.level = Some(value.trim().parse().map_err(|_| {
+                    format!("line {}: '{}' is not a bookmark level", line_number, value)
+                })?)
+            }
+            "BookmarkPageNumber" => {
+                bookmark.page_number = Some(value.trim().parse().map_err(|_| {
+                    format!("line {}: '{}' is not a page number", line_number, value)
+                })?)
+            }
+            _ => (),
+        }
+    }
+
+    let mut outline = PdfOutline::new();
+    let mut depth = 0;
+    let mut previous_page = 1;
+    for bookmark in bookmarks {
+        let missing =
+            |field: &str| format!("line {}: bookmark has no {}", bookmark.line_number, field);
+        let title = bookmark.title.ok_or_else(|| missing("BookmarkTitle"))?;
+        let level = bookmark.level.ok_or_else(|| missing("BookmarkLevel"))?;
+        //pdftk writes page 0 for bookmarks that don't point to a page
+        let page_number = match bookmark.page_number {
+            Some(0) => previous_page,
+            Some(page_number) => page_number,
+            None => return Err(missing("BookmarkPageNumber")),
+        };
+        previous_page = page_number;
+
+        if level == 0 || level > depth + 1 {
+            return Err(format!(
+                "line {}: bookmark level {} follows level {}",
+                bookmark.line_number, level, depth
+            ));
+        }
+        depth = level;
+        push_at_depth(
+            &mut outline,
+            level - 1,
+            PdfOutlineEntry::new(page_number, title),
+        );
+    }
+    Ok(outline)
+}
+
+/// Adds an entry as the last child of the last entry one level up.
+fn push_at_depth(outline: &mut PdfOutline, depth: usize, entry: PdfOutlineEntry) {
+    let parent = (0..depth).try_fold(outline, |parent, _| {
+        parent.last_mut().map(|entry| &mut entry.children)
+    });
+    if let Some(parent) = parent {
+        parent.push(entry);
+    }
+}
+
+/// Writes characters outside printable ASCII and XML special characters as entities,
+/// like `pdftk dump_data` does. Line breaks would otherwise end the record.
+fn encode_pdftk_entities(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => encoded.push_str("&amp;"),
+            '<' => encoded.push_str("&lt;"),
+            '>' => encoded.push_str("&gt;"),
+            '"' => encoded.push_str("&quot;"),
+            c if c.is_ascii() && !c.is_ascii_control() => encoded.push(c),
+            c => encoded.push_str(&format!("&#{};", c as u32)),
+        }
+    }
+    encoded
+}
+
+/// `pdftk dump_data` writes characters outside ASCII and XML special characters as entities.
+fn decode_pdftk_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .map(|end| (&rest[1..end], end))
+            .and_then(|(name, end)| {
+                let c = match name {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    }
+                    .and_then(char::from_u32),
+                };
+                c.map(|c| (c, end))
+            });
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[derive(Serialize, Deserialize)]
+struct QpdfJson {
+    #[serde(default)]
+    version: u32,
+    outlines: Vec<QpdfOutlineItem>,
+}
+
+/// An item of the `outlines` section. Object references are specific to the file
+/// qpdf read, so only the page number is used.
+#[derive(Serialize, Deserialize)]
+struct QpdfOutlineItem {
+    title: String,
+    /// `null` if the item doesn't point to a page
+    destpageposfrom1: Option<u32>,
+    #[serde(default)]
+    open: bool,
+    #[serde(default)]
+    kids: Vec<QpdfOutlineItem>,
+}
+
+/// Version of the qpdf JSON format that is written
+const QPDF_JSON_VERSION: u32 = 2;
+
+pub fn outline_to_qpdf_json(outline: &PdfOutline) -> Result<String, String> {
+    fn items(outline: &PdfOutline) -> Vec<QpdfOutlineItem> {
+        outline
+            .iter()
+            .map(|entry| QpdfOutlineItem {
+                title: entry.title.clone(),
+                destpageposfrom1: Some(entry.page_number),
+                open: false,
+                kids: items(&entry.children),
+            })
+            .collect()
+    }
+
+    let json = QpdfJson {
+        version: QPDF_JSON_VERSION,
+        outlines: items(outline),
+    };
+    serde_json::to_string_pretty(&json).map_err(|e| e.to_string())
+}
+
+/// Reads the `outlines` section of `qpdf --json` output, ignoring everything else in it.
+/// Items that don't point to a page are treated as being on the page of the item before them.
+pub fn outline_from_qpdf_json(text: &str) -> Result<PdfOutline, String> {
+    fn entries(items: Vec<QpdfOutlineItem>, previous_page: &mut u32) -> PdfOutline {
+        items
+            .into_iter()
+            .map(|item| {
+                let page_number = item.destpageposfrom1.unwrap_or(*previous_page);
+                *previous_page = page_number;
+                let mut entry = PdfOutlineEntry::new(page_number, item.title);
+                entry.children = entries(item.kids, previous_page);
+                entry
+            })
+            .collect()
+    }
+
+    let json: QpdfJson = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    Ok(entries(json.outlines, &mut 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_outline() -> PdfOutline {
+        let mut chapter = PdfOutlineEntry::new(1, String::from("A &amp; B <C> \"D\""));
+        chapter.children = vec![
+            PdfOutlineEntry::new(2, String::from("Café – ü")),
+            PdfOutlineEntry::new(3, String::from("Two\nlines")),
+        ];
+        vec![chapter, PdfOutlineEntry::new(4, String::from("Plain"))]
+    }
+
+    #[test]
+    fn pdftk_round_trip() {
+        let outline = sample_outline();
+        let text = outline_to_pdftk(&outline);
+        assert!(text.contains("BookmarkTitle: A &amp;amp; B &lt;C&gt; &quot;D&quot;\n"));
+        assert!(text.contains("BookmarkTitle: Two&#10;lines\n"));
+        assert_eq!(outline_from_pdftk(&text).unwrap(), outline);
+    }
+
+    #[test]
+    fn qpdf_round_trip() {
+        let outline = sample_outline();
+        let text = outline_to_qpdf_json(&outline).unwrap();
+        assert_eq!(outline_from_qpdf_json(&text).unwrap(), outline);
+    }
+}
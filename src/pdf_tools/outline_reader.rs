@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use super::{
+    error::PdfError,
+    pdf_outline::{PdfOutline, PdfOutlineEntry},
+};
+
+pub trait PdfOutlineReader {
+    fn read_outline(&self) -> Result<PdfOutline, PdfError>;
+}
+
+impl PdfOutlineReader for Document {
+    /// Returns the document's outline, or an empty one if it has none.
+    ///
+    /// Entries whose destination is not a page in this document, like links to websites,
+    /// are kept and treated as being on the page of the entry before them.
+    fn read_outline(&self) -> Result<PdfOutline, PdfError> {
+        let outlines = match self.catalog()?.get_deref(b"Outlines", self) {
+            Ok(Object::Dictionary(outlines)) => outlines,
+            _ => return Ok(PdfOutline::new()),
+        };
+        let page_numbers = self
+            .get_pages()
+            .into_iter()
+            .map(|(number, id)| (id, number))
+            .collect();
+
+        let mut visited = BTreeSet::new();
+        let mut previous_page = 1;
+        Ok(read_entries(
+            self,
+            &child_items(self, outlines),
+            &page_numbers,
+            &mut visited,
+            &mut previous_page,
+        ))
+    }
+}
+
+fn read_entries(
+    doc: &Document,
+    items: &[ObjectId],
+    page_numbers: &BTreeMap<ObjectId, u32>,
+    visited: &mut BTreeSet<ObjectId>,
+    previous_page: &mut u32,
+) -> PdfOutline {
+    let mut entries = PdfOutline::new();
+    for &id in items {
+        //a malformed outline may contain an item more than once
+        if !visited.insert(id) {
+            continue;
+        }
+        let Ok(item) = doc.get_dictionary(id) else {
+            continue;
+        };
+
+        let destination = item_destination(doc, item);
+        let page_number = destination
+            .and_then(|destination| destination_page(destination, page_numbers))
+            .unwrap_or(*previous_page);
+        *previous_page = page_number;
+
+        let title = item
+            .get_deref(b"Title", doc)
+            .and_then(Object::as_str)
+            .map(decode_text_string)
+            .unwrap_or_default();
+        let mut entry = PdfOutlineEntry::new(page_number, title);
+        entry.position = destination.and_then(destination_position);
+        entry.children = read_entries(
+            doc,
+            &child_items(doc, item),
+            page_numbers,
+            visited,
+            previous_page,
+        );
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Returns the children of an outline item or of the outline root.
+fn child_items(doc: &Document, item: &Dictionary) -> Vec<ObjectId> {
+    if let Ok(first) = item.get(b"First").and_then(Object::as_reference) {
+        return outline_items(doc, first);
+    }
+    //some producers list children like a page tree does
+    item.get_deref(b"Kids", doc)
+        .and_then(Object::as_array)
+        .map(|kids| {
+            kids.iter()
+                .filter_map(|kid| kid.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn first_child(doc: &Document, id: ObjectId) -> Option<ObjectId> {
+    doc.get_dictionary(id)
+        .and_then(|item| item.get(b"First"))
+        .and_then(Object::as_reference)
+        .ok()
+}
+
+/// Returns the outline item `first` and its following siblings.
+pub fn outline_items(doc: &Document, first: ObjectId) -> Vec<ObjectId> {
+    let mut items = vec![];
+    let mut seen = BTreeSet::new();
+    let mut next = Some(first);
+    while let Some(id) = next {
+        //a malformed outline may link back to an earlier item
+        if !seen.insert(id) {
+            break;
+        }
+        items.push(id);
+        next = doc
+            .get_dictionary(id)
+            .and_then(|item| item.get(b"Next"))
+            .and_then(Object::as_reference)
+            .ok();
+    }
+    items
+}
+
+/// Returns the explicit destination array an outline item points to, either through
+/// its `Dest` or a `GoTo` action, resolving named destinations.
+pub fn item_destination<'a>(doc: &'a Document, item: &'a Dictionary) -> Option<&'a [Object]> {
+    let destination = match item.get_deref(b"A", doc) {
+        Ok(Object::Dictionary(action)) => {
+            if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+                return None;
+            }
+            action.get_deref(b"D", doc).ok()?
+        }
+        _ => item.get_deref(b"Dest", doc).ok()?,
+    };
+    match destination {
+        Object::Array(destination) => Some(destination),
+        Object::Name(name) => named_destination(doc, name),
+        Object::String(name, _) => named_destination(doc, name),
+        _ => None,
+    }
+}
+
+/// Looks up a named destination, in the catalog's `Dests` dictionary (PDF 1.1)
+/// or in the `Dests` name tree (PDF 1.2 and later).
+fn named_destination<'a>(doc: &'a Document, name: &[u8]) -> Option<&'a [Object]> {
+    let catalog = doc.catalog().ok()?;
+    let destination = catalog
+        .get_deref(b"Dests", doc)
+        .and_then(Object::as_dict)
+        .and_then(|dests| dests.get_deref(name, doc))
+        .ok()
+        .or_else(|| {
+            let tree = catalog
+                .get_deref(b"Names", doc)
+                .and_then(Object::as_dict)
+                .and_then(|names| names.get_deref(b"Dests", doc))
+                .and_then(Object::as_dict)
+                .ok()?;
+            name_tree_lookup(doc, tree, name, 0)
+        })?;
+
+    //the value is either the destination itself or a dictionary holding it
+    match destination {
+        Object::Array(destination) => Some(destination),
+        Object::Dictionary(dict) => dict
+            .get_deref(b"D", doc)
+            .and_then(Object::as_array)
+            .ok()
+            .map(Vec::as_slice),
+        _ => None,
+    }
+}
+
+/// Name trees nested deeper than this are assumed to contain a loop.
+const MAX_NAME_TREE_DEPTH: usize = 32;
+
+fn name_tree_lookup<'a>(
+    doc: &'a Document,
+    node: &'a Dictionary,
+    name: &[u8],
+    depth: usize,
+) -> Option<&'a Object> {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return None;
+    }
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        let found = names.chunks_exact(2).find_map(|pair| match &pair[0] {
+            Object::String(key, _) if key == name => doc.dereference(&pair[1]).ok(),
+            _ => None,
+        });
+        if let Some((_, value)) = found {
+            return Some(value);
+        }
+    }
+    let kids = node
+        .get_deref(b"Kids", doc)
+        .and_then(Object::as_array)
+        .ok()?;
+    kids.iter()
+        .filter_map(|kid| doc.dereference(kid).ok()?.1.as_dict().ok())
+        .find_map(|kid| name_tree_lookup(doc, kid, name, depth + 1))
+}
+
+/// Returns the number of the page a destination array points to.
+pub fn destination_page(
+    destination: &[Object],
+    page_numbers: &BTreeMap<ObjectId, u32>,
+) -> Option<u32> {
+    match destination.first()? {
+        Object::Reference(page_id) => page_numbers.get(page_id).copied(),
+        //page indices are only valid in remote destinations, but some producers use them anyway
+        Object::Integer(index) => u32::try_from(*index)
+            .ok()
+            .map(|index| index + 1)
+            .filter(|number| page_numbers.values().any(|n| n == number)),
+        _ => None,
+    }
+}
+
+/// Returns the left and top of an `XYZ` destination if both are given.
+fn destination_position(destination: &[Object]) -> Option<(f32, f32)> {
+    match destination {
+        [_, Object::Name(kind), left, top, ..] if kind == b"XYZ" => {
+            Some((left.as_float().ok()?, top.as_float().ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes a PDF text string, which is either UTF-16BE with a byte order mark
+/// or PDFDocEncoding.
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, utf16 @ ..] => {
+            let units = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        [0xEF, 0xBB, 0xBF, utf8 @ ..] => String::from_utf8_lossy(utf8).into_owned(),
+        _ => bytes.iter().map(|&b| pdf_doc_char(b)).collect(),
+    }
+}
+
+/// The characters of PDFDocEncoding from 0x18 to 0x1F, which are accents.
+const PDF_DOC_ACCENTS: [char; 8] = ['˘', 'ˇ', 'ˆ', '˙', '˝', '˛', '˚', '˜'];
+/// The characters of PDFDocEncoding from 0x80 to 0xA0, 0x9F being undefined.
+const PDF_DOC_PUNCTUATION: [char; 33] = [
+    '•',
+    '†',
+    '‡',
+    '…',
+    '—',
+    '–',
+    'ƒ',
+    '⁄',
+    '‹',
+    '›',
+    '−',
+    '‰',
+    '„',
+    '“',
+    '”',
+    '‘',
+    '’',
+    '‚',
+    '™',
+    'ﬁ',
+    'ﬂ',
+    'Ł',
+    'Œ',
+    'Š',
+    'Ÿ',
+    'Ž',
+    'ı',
+    'ł',
+    'œ',
+    'š',
+    'ž',
+    char::REPLACEMENT_CHARACTER,
+    '€',
+];
+
+/// Decodes a byte of PDFDocEncoding, which matches Latin-1 apart from the accents
+/// and punctuation in its control character ranges.
+fn pdf_doc_char(byte: u8) -> char {
+    match byte {
+        0x18..=0x1F => PDF_DOC_ACCENTS[usize::from(byte - 0x18)],
+        0x80..=0xA0 => PDF_DOC_PUNCTUATION[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_without_a_byte_order_mark_are_pdf_doc_encoded() {
+        assert_eq!(decode_text_string(b"Caf\xE9"), "Café");
+        assert_eq!(
+            decode_text_string(b"1 \x85 \x8DQuoted\x8E \x84 \x93nal\x92"),
+            "1 – “Quoted” — ﬁnal™"
+        );
+        assert_eq!(decode_text_string(b"\x18\x1F\x80\x90\xA0"), "˘˜•’€");
+    }
+
+    #[test]
+    fn titles_with_a_byte_order_mark_are_unicode() {
+        assert_eq!(decode_text_string(b"\xFE\xFF\x00A\x20\x13"), "A–");
+        assert_eq!(decode_text_string(b"\xEF\xBB\xBF\xE2\x80\x93"), "–");
+    }
+}
@@ -0,0 +1,228 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use super::{
+    error::PdfError,
+    font_decoder::{FontDecoder, FontDecoders},
+};
+
+/// A font a page selects by its resource name.
+#[derive(Debug, Default)]
+pub struct PageFont {
+    /// Empty if the font is missing from the page's resources, or its name could not be resolved
+    pub name: String,
+    pub decoder: Arc<FontDecoder>,
+}
+
+/// The fonts in a page's resources, each resolved the first time the page selects it.
+pub struct PageFonts<'a> {
+    doc: &'a Document,
+    page_number: u32,
+    page_id: ObjectId,
+    resources: BTreeMap<Vec<u8>, &'a Dictionary>,
+    resolved: BTreeMap<Vec<u8>, PageFont>,
+    font_decoders: &'a FontDecoders,
+}
+
+impl<'a> PageFonts<'a> {
+    pub fn new(
+        doc: &'a Document,
+        page_number: u32,
+        page_id: ObjectId,
+        font_decoders: &'a FontDecoders,
+    ) -> Self {
+        PageFonts {
+            doc,
+            page_number,
+            page_id,
+            resources: doc.get_page_fonts(page_id),
+            resolved: BTreeMap::new(),
+            font_decoders,
+        }
+    }
+
+    /// Returns the font the page selects by `resource_name`. A font that is missing
+    /// or unnamed is reported to `problems` the first time, its text is skipped.
+    pub fn get(&mut self, resource_name: &[u8], problems: &mut Vec<PdfError>) -> &PageFont {
+        if !self.resolved.contains_key(resource_name) {
+            let font = self.resolve(resource_name, problems);
+            self.resolved.insert(resource_name.to_vec(), font);
+        }
+        &self.resolved[resource_name]
+    }
+
+    fn resolve(&self, resource_name: &[u8], problems: &mut Vec<PdfError>) -> PageFont {
+        let resource = || String::from_utf8_lossy(resource_name).into_owned();
+        let Some(font) = self.resources.get(resource_name) else {
+            problems.push(PdfError::MissingFontResource {
+                page_number: self.page_number,
+                resource: resource(),
+            });
+            return PageFont::default();
+        };
+        let font_id = font_id(self.doc, self.page_id, resource_name);
+        let Some(name) = font_name(self.doc, font_id, resource_name, font) else {
+            problems.push(PdfError::UnnamedFont {
+                page_number: self.page_number,
+                resource: resource(),
+            });
+            return PageFont::default();
+        };
+        let decoder = match font_id {
+            Some(font_id) => self.font_decoders.get(self.doc, font_id, font),
+            //a font written into the resources belongs to this page alone
+            None => Arc::new(FontDecoder::new(self.doc, font)),
+        };
+        PageFont { name, decoder }
+    }
+}
+
+/// Resolves the name shown for a font dictionary.
+///
+/// Composite (Type0) fonts are named after their descendant CIDFont. Type3 fonts usually
+/// have no `BaseFont`, and are named after their obsolete `Name` entry, or their object id,
+/// since pages are free to use the same resource name for different fonts.
+fn font_name(
+    doc: &Document,
+    font_id: Option<ObjectId>,
+    resource_name: &[u8],
+    font: &Dictionary,
+) -> Option<String> {
+    let base_font = |font: &Dictionary| {
+        font.get(b"BaseFont")
+            .and_then(|name| name.as_name_str())
+            .ok()
+            .map(str::to_string)
+    };
+
+    match font.get(b"Subtype").and_then(|subtype| subtype.as_name()) {
+        Ok(b"Type0") => font
+            .get_deref(b"DescendantFonts", doc)
+            .and_then(|descendants| descendants.as_array())
+            .ok()
+            .and_then(|descendants| descendants.first())
+            .and_then(|descendant| doc.dereference(descendant).ok())
+            .and_then(|(_, descendant)| descendant.as_dict().ok())
+            .and_then(base_font)
+            .or_else(|| base_font(font)),
+        Ok(b"Type3") => base_font(font).or_else(|| {
+            let name = match (font.get(b"Name").and_then(Object::as_name), font_id) {
+                (Ok(name), _) => String::from_utf8_lossy(name).into_owned(),
+                (_, Some((number, generation))) => format!("{}-{}", number, generation),
+                //a font written into the resources has no id
+                (_, None) => String::from_utf8_lossy(resource_name).into_owned(),
+            };
+            Some(format!("Type3-{}", name))
+        }),
+        _ => base_font(font),
+    }
+}
+
+/// Returns the object id of the font a page selects by `resource_name`, looking
+/// through the page's resources in the same order as `Document::get_page_fonts`.
+fn font_id(doc: &Document, page_id: ObjectId, resource_name: &[u8]) -> Option<ObjectId> {
+    let (page_resources, inherited_ids) = doc.get_page_resources(page_id);
+    page_resources
+        .into_iter()
+        .chain(
+            inherited_ids
+                .into_iter()
+                .filter_map(|id| doc.get_dictionary(id).ok()),
+        )
+        .filter_map(|resources| resources.get(b"Font").and_then(Object::as_dict).ok())
+        .find_map(|fonts| fonts.get(resource_name).ok())
+        .and_then(|font| font.as_reference().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+
+    use super::*;
+
+    /// Builds a document with a page for each font, where it is the resource `F1`.
+    fn document_with_fonts(fonts: Vec<Dictionary>) -> (Document, Vec<ObjectId>) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let page_ids: Vec<ObjectId> = fonts
+            .into_iter()
+            .map(|font| {
+                let font_id = doc.add_object(font);
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Resources" => dictionary! {
+                        "Font" => dictionary! { "F1" => font_id },
+                    },
+                })
+            })
+            .collect();
+        let kids: Vec<Object> = page_ids.iter().map(|&id| id.into()).collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+            }),
+        );
+        (doc, page_ids)
+    }
+
+    fn font_on_page(doc: &Document, page_id: ObjectId) -> String {
+        let font_decoders = FontDecoders::default();
+        let mut fonts = PageFonts::new(doc, 1, page_id, &font_decoders);
+        fonts.get(b"F1", &mut vec![]).name.clone()
+    }
+
+    #[test]
+    fn type3_fonts_with_the_same_resource_name_are_told_apart() {
+        let type3 = || dictionary! { "Type" => "Font", "Subtype" => "Type3" };
+        let (doc, page_ids) = document_with_fonts(vec![type3(), type3()]);
+        let first = font_on_page(&doc, page_ids[0]);
+        let second = font_on_page(&doc, page_ids[1]);
+        assert!(first.starts_with("Type3-"));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn type3_fonts_are_named_after_their_name_entry() {
+        let font = dictionary! { "Type" => "Font", "Subtype" => "Type3", "Name" => "Logo" };
+        let (doc, page_ids) = document_with_fonts(vec![font.clone(), font]);
+        assert_eq!(font_on_page(&doc, page_ids[0]), "Type3-Logo");
+        assert_eq!(font_on_page(&doc, page_ids[1]), "Type3-Logo");
+    }
+
+    #[test]
+    fn composite_fonts_are_named_after_their_descendant() {
+        let descendant = dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => "NotoSans-Bold",
+        };
+        let font = dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => "NotoSans-Bold-Identity-H",
+            "DescendantFonts" => vec![Object::Dictionary(descendant)],
+        };
+        let (doc, page_ids) = document_with_fonts(vec![font]);
+        assert_eq!(font_on_page(&doc, page_ids[0]), "NotoSans-Bold");
+    }
+
+    #[test]
+    fn unnamed_fonts_are_reported_once() {
+        let font = dictionary! { "Type" => "Font", "Subtype" => "Type1" };
+        let (doc, page_ids) = document_with_fonts(vec![font]);
+        let font_decoders = FontDecoders::default();
+        let mut fonts = PageFonts::new(&doc, 1, page_ids[0], &font_decoders);
+        let mut problems = vec![];
+        assert_eq!(fonts.get(b"F1", &mut problems).name, "");
+        assert_eq!(fonts.get(b"F1", &mut problems).name, "");
+        assert!(matches!(
+            &problems[..],
+            [PdfError::UnnamedFont { page_number: 1, resource }] if resource == "F1"
+        ));
+    }
+}
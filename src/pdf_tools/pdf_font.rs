@@ -7,7 +7,6 @@ pub struct PdfFont {
 }
 
 impl PdfFont {
-    #[allow(dead_code)]
     pub fn new(base_font: String, size: (f32, f32)) -> Self {
         PdfFont { size, base_font }
     }
@@ -39,6 +38,35 @@ impl Ord for PdfFont {
     }
 }
 
+impl std::str::FromStr for PdfFont {
+    type Err = String;
+
+    /// Parses a font written as `BaseFont:size` or `BaseFont:width,height`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (base_font, size) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected BaseFont:size, found '{}'", s))?;
+        if base_font.is_empty() {
+            return Err(format!("missing base font name in '{}'", s));
+        }
+
+        //sizes are compared and divided by, so only positive numbers are valid
+        let parse_size = |size: &str| match size.trim().parse::<f32>() {
+            Ok(size) if size.is_finite() && size > 0.0 => Ok(size),
+            _ => Err(format!("invalid font size '{}' in '{}'", size, s)),
+        };
+        let size = match size.split_once(',') {
+            Some((x, y)) => (parse_size(x)?, parse_size(y)?),
+            None => {
+                let size = parse_size(size)?;
+                (size, size)
+            }
+        };
+
+        Ok(PdfFont::new(base_font.to_string(), size))
+    }
+}
+
 impl std::fmt::Display for PdfFont {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{} ", self.base_font)?;
@@ -49,3 +77,65 @@ impl std::fmt::Display for PdfFont {
         }
     }
 }
+
+/// How a font is used in a document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FontStats {
+    /// Number of pages the font shows text on
+    pub page_count: usize,
+    /// Number of separate pieces of text shown in the font, each on a line of its own
+    /// or between text in other fonts
+    pub runs: usize,
+    /// Number of non-whitespace characters shown in the font
+    pub glyphs: usize,
+    pub first_page: u32,
+    pub last_page: u32,
+    /// The first few distinct pieces of text shown in the font
+    pub samples: Vec<TextSample>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextSample {
+    pub page_number: u32,
+    pub text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fonts_parse_with_one_or_two_sizes() {
+        let font: PdfFont = "Helvetica-Bold:18".parse().unwrap();
+        assert_eq!(
+            font,
+            PdfFont::new(String::from("Helvetica-Bold"), (18.0, 18.0))
+        );
+        let font: PdfFont = "Times:Roman:10.5, 12".parse().unwrap();
+        assert_eq!(
+            font,
+            PdfFont::new(String::from("Times:Roman"), (10.5, 12.0))
+        );
+    }
+
+    #[test]
+    fn malformed_fonts_are_rejected() {
+        for font in [
+            "Helvetica",
+            ":12",
+            "Helvetica:",
+            "Helvetica:big",
+            "Helvetica:12,",
+        ] {
+            assert!(font.parse::<PdfFont>().is_err(), "{}", font);
+        }
+    }
+
+    #[test]
+    fn sizes_must_be_positive_numbers() {
+        for size in ["0", "-12", "NaN", "inf", "-inf", "12,0", "12,NaN"] {
+            let font = format!("Helvetica:{}", size);
+            assert!(font.parse::<PdfFont>().is_err(), "{}", font);
+        }
+    }
+}
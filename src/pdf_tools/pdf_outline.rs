@@ -1,7 +1,17 @@
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+use super::outline_interchange::{
+    outline_from_pdftk, outline_from_qpdf_json, outline_to_pdftk, outline_to_qpdf_json,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PdfOutlineEntry {
     pub page_number: u32,
     pub title: String,
+    /// The left and top of the heading on its page, in default user space
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<(f32, f32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<PdfOutlineEntry>,
 }
 
@@ -10,6 +20,7 @@ impl PdfOutlineEntry {
         Self {
             page_number,
             title,
+            position: None,
             children: Vec::<PdfOutlineEntry>::default(),
         }
     }
@@ -17,16 +28,259 @@ impl PdfOutlineEntry {
 
 pub type PdfOutline = Vec<PdfOutlineEntry>;
 
-pub fn print_outline(outline: &PdfOutline) {
-    recursive_print_outline(outline, 0);
+/// A file format outlines can be exported to and imported from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlineFormat {
+    /// One entry per line as `title<TAB>page`, indented by two spaces per level
+    Text,
+    Json,
+    Yaml,
+    /// The `BookmarkBegin` records of `pdftk dump_data`
+    Pdftk,
+    /// The `outlines` section of `qpdf --json`
+    Qpdf,
+}
+
+impl OutlineFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutlineFormat::Text | OutlineFormat::Pdftk => "txt",
+            OutlineFormat::Json | OutlineFormat::Qpdf => "json",
+            OutlineFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Tells pdftk and qpdf dumps apart from the formats that share their extension.
+    pub fn refine_by_content(self, text: &str) -> Self {
+        match self {
+            OutlineFormat::Text if text.lines().any(|line| line.trim() == "BookmarkBegin") => {
+                OutlineFormat::Pdftk
+            }
+            //outlines are arrays, qpdf writes an object
+            OutlineFormat::Json if text.trim_start().starts_with('{') => OutlineFormat::Qpdf,
+            format => format,
+        }
+    }
+}
+
+impl std::str::FromStr for OutlineFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(OutlineFormat::Text),
+            "json" => Ok(OutlineFormat::Json),
+            "yaml" | "yml" => Ok(OutlineFormat::Yaml),
+            "pdftk" => Ok(OutlineFormat::Pdftk),
+            "qpdf" => Ok(OutlineFormat::Qpdf),
+            _ => Err(format!(
+                "unknown outline format '{}', expected text, json, yaml, pdftk or qpdf",
+                s
+            )),
+        }
+    }
 }
 
-fn recursive_print_outline(outline: &PdfOutline, depth: usize) {
+pub fn export_outline(outline: &PdfOutline, format: OutlineFormat) -> Result<String, String> {
+    match format {
+        OutlineFormat::Text => Ok(outline_to_text(outline)),
+        OutlineFormat::Json => serde_json::to_string_pretty(outline).map_err(|e| e.to_string()),
+        OutlineFormat::Yaml => serde_yaml::to_string(outline).map_err(|e| e.to_string()),
+        OutlineFormat::Pdftk => Ok(outline_to_pdftk(outline)),
+        OutlineFormat::Qpdf => outline_to_qpdf_json(outline),
+    }
+}
+
+pub fn outline_to_text(outline: &PdfOutline) -> String {
+    let mut text = String::new();
+    recursive_outline_to_text(outline, 0, &mut text);
+    text
+}
+
+fn recursive_outline_to_text(outline: &PdfOutline, depth: usize, text: &mut String) {
     for entry in outline {
         for _ in 0..depth {
-            print!(" ");
+            text.push_str("  ");
+        }
+        //keep every entry on one line with a single tab before the page number
+        let title = entry.title.replace(['\t', '\r', '\n'], " ");
+        text.push_str(&format!("{}\t{}\n", title, entry.page_number));
+        recursive_outline_to_text(&entry.children, depth + 1, text);
+    }
+}
+
+/// Parses an outline in the given format. Errors include the line they occurred on.
+pub fn import_outline(text: &str, format: OutlineFormat) -> Result<PdfOutline, String> {
+    match format {
+        OutlineFormat::Text => outline_from_text(text),
+        OutlineFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+        OutlineFormat::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+        OutlineFormat::Pdftk => outline_from_pdftk(text),
+        OutlineFormat::Qpdf => outline_from_qpdf_json(text),
+    }
+}
+
+/// Parses the text format written by [`outline_to_text`]. Any consistent indentation
+/// is accepted, and blank lines are skipped.
+pub fn outline_from_text(text: &str) -> Result<PdfOutline, String> {
+    let mut outline = PdfOutline::new();
+    //the indentation of the current entry and each of its parents
+    let mut indents: Vec<usize> = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+
+        let (title, page) = content.rsplit_once('\t').ok_or_else(|| {
+            format!(
+                "line {}: expected a title and a page number separated by a tab",
+                line_number
+            )
+        })?;
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(format!("line {}: missing title", line_number));
+        }
+        let page_number = match page.trim().parse::<u32>() {
+            Ok(0) => return Err(format!("line {}: page numbers start at 1", line_number)),
+            Ok(page_number) => page_number,
+            Err(_) => {
+                return Err(format!(
+                    "line {}: '{}' is not a page number",
+                    line_number,
+                    page.trim()
+                ))
+            }
+        };
+
+        //an entry is a child of the last entry indented less than it, and a sibling
+        //of one indented the same; dedenting must return to an earlier indentation
+        let mut dedented = false;
+        while indents.last().map_or(false, |&last| last > indent) {
+            indents.pop();
+            dedented = true;
+        }
+        match indents.last() {
+            Some(&last) if last == indent => {
+                indents.pop();
+            }
+            None if outline.is_empty() => (),
+            Some(_) if !dedented => (),
+            _ => {
+                return Err(format!(
+                    "line {}: indentation does not match any previous line",
+                    line_number
+                ))
+            }
         }
-        println!("{}  {}", entry.title, entry.page_number);
-        recursive_print_outline(&entry.children, depth + 1);
+
+        //every level in `indents` has an entry, so the parent always exists
+        let parent = (0..indents.len()).try_fold(&mut outline, |parent, _| {
+            parent.last_mut().map(|entry| &mut entry.children)
+        });
+        if let Some(parent) = parent {
+            parent.push(PdfOutlineEntry::new(page_number, title.to_string()));
+            indents.push(indent);
+        }
+    }
+    Ok(outline)
+}
+
+/// Checks that every entry is on a page of a document with `page_count` pages.
+pub fn check_page_numbers(outline: &PdfOutline, page_count: u32) -> Result<(), String> {
+    for entry in outline {
+        if entry.page_number == 0 {
+            return Err(format!(
+                "'{}' is on page 0, but page numbers start at 1",
+                entry.title
+            ));
+        }
+        if entry.page_number > page_count {
+            return Err(format!(
+                "'{}' is on page {}, but the document has {} pages",
+                entry.title, entry.page_number, page_count
+            ));
+        }
+        check_page_numbers(&entry.children, page_count)?;
+    }
+    Ok(())
+}
+
+pub fn print_outline(outline: &PdfOutline) {
+    print!("{}", outline_to_text(outline));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let mut chapter = PdfOutlineEntry::new(1, String::from("Chapter"));
+        chapter.children = vec![PdfOutlineEntry::new(2, String::from("Section"))];
+        let outline = vec![chapter, PdfOutlineEntry::new(3, String::from("Appendix"))];
+        assert_eq!(
+            outline_from_text(&outline_to_text(&outline)).unwrap(),
+            outline
+        );
+    }
+
+    #[test]
+    fn text_accepts_any_consistent_indentation() {
+        let outline = outline_from_text("A\t1\n\n\tB\t2\n\t\tC\t3\nD\t4\n").unwrap();
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].children[0].children[0].title, "C");
+        assert_eq!(outline[1].title, "D");
+    }
+
+    #[test]
+    fn text_rejects_unmatched_indentation() {
+        assert_eq!(
+            outline_from_text("A\t1\n    B\t2\n  C\t3\n").unwrap_err(),
+            "line 3: indentation does not match any previous line"
+        );
+    }
+
+    #[test]
+    fn text_requires_a_tab() {
+        assert_eq!(
+            outline_from_text("A\t1\nB 2\n").unwrap_err(),
+            "line 2: expected a title and a page number separated by a tab"
+        );
+    }
+
+    #[test]
+    fn text_errors_name_their_line() {
+        assert_eq!(
+            outline_from_text("A\t1\n\nB\tten\n").unwrap_err(),
+            "line 3: 'ten' is not a page number"
+        );
+        assert_eq!(
+            outline_from_text("A\t0\n").unwrap_err(),
+            "line 1: page numbers start at 1"
+        );
+    }
+
+    #[test]
+    fn page_numbers_must_be_in_the_document() {
+        let outline = import_outline(
+            r#"[{"page_number": 1, "title": "A", "children": [{"page_number": 0, "title": "B"}]}]"#,
+            OutlineFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(
+            check_page_numbers(&outline, 5).unwrap_err(),
+            "'B' is on page 0, but page numbers start at 1"
+        );
+        let outline = vec![PdfOutlineEntry::new(6, String::from("C"))];
+        assert_eq!(
+            check_page_numbers(&outline, 5).unwrap_err(),
+            "'C' is on page 6, but the document has 5 pages"
+        );
+        assert!(check_page_numbers(&outline, 6).is_ok());
     }
 }
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use lopdf::{content::Operation, Document, Object, ObjectId};
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use rayon::prelude::*;
+
+use super::{
+    error::PdfError,
+    font_decoder::{FontDecoder, FontDecoders},
+    graphics_state::{
+        GraphicsState, BEGIN_TEXT, END_TEXT, MOVE_TEXT, MOVE_TEXT_SET_LEADING, NEXT_LINE,
+        NEXT_LINE_SHOW_SPACED_TEXT, NEXT_LINE_SHOW_TEXT, SET_TEXT_FONT, SET_TEXT_MATRIX, STATE_OPS,
+    },
+    page_fonts::PageFonts,
+    pdf_font::PdfFont,
+};
+
+pub const DISPLAY_TEXT_OPS: [&str; 4] = ["Tj", "'", "\"", "TJ"];
+
+/// What separates a run of text from the run before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunBreak {
+    /// The text continues on the same line in another font
+    FontChange,
+    /// The text moved to the next line of the same text object
+    Line,
+    /// A new text object was begun, or the text matrix was set
+    TextObject,
+}
+
+/// Text shown in one font on one line, in content stream order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    /// Empty if the font name could not be resolved
+    pub font: PdfFont,
+    /// The decoded text, which may be empty or only whitespace
+    pub text: String,
+    /// The start of the line the run begins on, in default user space
+    pub position: (f32, f32),
+    pub run_break: RunBreak,
+}
+
+/// The text on a page, read from its content stream once.
+#[derive(Debug)]
+pub struct PageText {
+    pub page_number: u32,
+    /// The bottom and top of the page's media box
+    pub vertical_bounds: (f32, f32),
+    pub runs: Vec<TextRun>,
+    /// Problems that hid some of the page's text without stopping it from being read
+    pub problems: Vec<PdfError>,
+}
+
+impl PageText {
+    /// Decodes the page's content stream and splits the text shown on it into runs.
+    /// Operands that can't be read end the page, keeping the text before them,
+    /// and a page whose content can't be decoded has no text.
+    pub fn new(
+        doc: &Document,
+        page_number: u32,
+        page_id: ObjectId,
+        font_decoders: &FontDecoders,
+    ) -> Self {
+        let vertical_bounds = page_vertical_bounds(doc, page_id);
+        let contents = match doc.get_and_decode_page_content(page_id) {
+            Ok(contents) => contents,
+            Err(source) => {
+                return PageText {
+                    page_number,
+                    vertical_bounds,
+                    runs: vec![],
+                    problems: vec![PdfError::MalformedContent {
+                        page_number,
+                        source,
+                    }],
+                }
+            }
+        };
+        let mut fonts = PageFonts::new(doc, page_number, page_id, font_decoders);
+        let mut state = GraphicsState::default();
+
+        let mut runs: Vec<TextRun> = vec![];
+        let mut problems = vec![];
+        //the strongest break since the last text was shown
+        let mut pending_break = Some(RunBreak::TextObject);
+        for op in contents.operations {
+            match op.operator.as_str() {
+                BEGIN_TEXT | END_TEXT | SET_TEXT_MATRIX => {
+                    pending_break = Some(RunBreak::TextObject)
+                }
+                MOVE_TEXT
+                | MOVE_TEXT_SET_LEADING
+                | NEXT_LINE
+                | NEXT_LINE_SHOW_TEXT
+                | NEXT_LINE_SHOW_SPACED_TEXT => {
+                    pending_break = pending_break.or(Some(RunBreak::Line))
+                }
+                _ => (),
+            }
+            match op.operator.as_str() {
+                x if STATE_OPS.contains(&x) || DISPLAY_TEXT_OPS.contains(&x) => {
+                    //' and " move to the next line before showing text
+                    if let Err(source) = state.update(&op) {
+                        problems.push(PdfError::MalformedContent {
+                            page_number,
+                            source,
+                        });
+                        break;
+                    }
+                    //fonts are resolved when they are set, so that each problem is reported once
+                    if let (SET_TEXT_FONT, Some(resource)) =
+                        (op.operator.as_str(), state.font_resource())
+                    {
+                        fonts.get(resource, &mut problems);
+                    }
+                }
+                _ => (),
+            }
+            if !DISPLAY_TEXT_OPS.contains(&op.operator.as_str()) {
+                continue;
+            }
+
+            let (font, decoder) = match state.font_resource() {
+                Some(resource) => {
+                    let page_font = fonts.get(resource, &mut problems);
+                    let font = PdfFont::new(page_font.name.clone(), state.font_size());
+                    (font, Arc::clone(&page_font.decoder))
+                }
+                None => (
+                    PdfFont::new(String::new(), state.font_size()),
+                    Default::default(),
+                ),
+            };
+            let text = text_from_operation(&op, &decoder).unwrap_or_default();
+            match (runs.last_mut(), pending_break) {
+                (Some(run), None) if run.font == font => run.text.push_str(&text),
+                (_, run_break) => runs.push(TextRun {
+                    font,
+                    text,
+                    position: state.text_position(),
+                    run_break: run_break.unwrap_or(RunBreak::FontChange),
+                }),
+            }
+            pending_break = None;
+        }
+
+        PageText {
+            page_number,
+            vertical_bounds,
+            runs,
+            problems,
+        }
+    }
+}
+
+/// The text of every page of a document, so that fonts can be listed and suggested,
+/// and outlines generated, without decoding the content streams again.
+#[derive(Debug, Default)]
+pub struct PdfTextIndex {
+    pub pages: Vec<PageText>,
+}
+
+impl PdfTextIndex {
+    pub fn new(doc: &Document) -> Self {
+        let pages: Vec<_> = doc.get_pages().into_iter().collect();
+        PdfTextIndex {
+            pages: read_pages(doc, &pages, &FontDecoders::default()),
+        }
+    }
+
+    /// Returns the problems met reading every page, in page order.
+    pub fn problems(&self) -> impl Iterator<Item = &PdfError> {
+        self.pages.iter().flat_map(|page| &page.problems)
+    }
+}
+
+/// Reads the text of pages given by their number and id, on every core with
+/// the `parallel` feature. The results are in the order of `pages` either way.
+/// `font_decoders` should be kept while the document's pages are read.
+pub fn read_pages(
+    doc: &Document,
+    pages: &[(u32, ObjectId)],
+    font_decoders: &FontDecoders,
+) -> Vec<PageText> {
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    let pages = pages.par_iter();
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    let pages = pages.iter();
+    pages
+        .map(|&(page_number, page_id)| PageText::new(doc, page_number, page_id, font_decoders))
+        .collect()
+}
+
+/// Adjustments in a `TJ` array, in thousandths of text space, that are
+/// wide enough to be treated as a space between words.
+const TJ_WORD_SPACING: f32 = 200.0;
+
+/// Decodes the text shown by a text-showing operation.
+fn text_from_operation(op: &Operation, decoder: &FontDecoder) -> Option<String> {
+    let string_object = match op.operator.as_str() {
+        "Tj" | "'" => op.operands.get(0)?,
+        "\"" => op.operands.get(2)?,
+        "TJ" => {
+            let mut text = String::new();
+            for element in op.operands.get(0)?.as_array().ok()? {
+                match element {
+                    Object::String(bytes, _) => text.push_str(&decoder.decode(bytes)),
+                    Object::Integer(_) | Object::Real(_) => {
+                        let adjustment = element.as_float().ok()?;
+                        if -adjustment > TJ_WORD_SPACING && !text.ends_with(' ') {
+                            text.push(' ');
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            return Some(text);
+        }
+        _ => return None,
+    };
+    Some(decoder.decode(string_object.as_str().ok()?))
+}
+
+/// Pages are this many levels deep in the page tree at most, unless it contains a loop.
+const MAX_PAGE_TREE_DEPTH: usize = 32;
+
+/// Returns the bottom and top of a page's media box, which may be inherited from the page tree.
+fn page_vertical_bounds(doc: &Document, page_id: ObjectId) -> (f32, f32) {
+    let mut node = doc.get_dictionary(page_id).ok();
+    for _ in 0..MAX_PAGE_TREE_DEPTH {
+        let Some(dict) = node else {
+            break;
+        };
+        if let Ok(media_box) = dict.get_deref(b"MediaBox", doc).and_then(Object::as_array) {
+            let coordinates: Vec<f32> =
+                media_box.iter().filter_map(|x| x.as_float().ok()).collect();
+            if let [_, y1, _, y2] = coordinates[..] {
+                return (y1.min(y2), y1.max(y2));
+            }
+        }
+        node = dict
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .ok();
+    }
+    //US Letter
+    (0.0, 792.0)
+}
@@ -1,14 +1,15 @@
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     fn saveFile(data: &[u8], filename: &str);
 }
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
-pub fn save_file_from_rust(data: Vec<u8>) {
-    let filename = "output.pdf";
-
+pub fn save_file_from_rust(data: Vec<u8>, filename: &str) -> Result<(), String> {
     // Convert Rust data to a byte slice
     let data_ptr = data.as_ptr();
     let data_len = data.len();
@@ -17,4 +18,45 @@ pub fn save_file_from_rust(data: Vec<u8>) {
     unsafe {
         saveFile(std::slice::from_raw_parts(data_ptr, data_len), filename);
     }
+    Ok(())
+}
+
+/// Asks the user where to save the file with a native "Save As" dialog, offering files
+/// with the extension of `filename`. Does nothing if the dialog is cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file_from_rust(data: Vec<u8>, filename: &str) -> Result<(), String> {
+    let mut dialog = rfd::FileDialog::new().set_file_name(filename);
+    if let Some((_, extension)) = filename.rsplit_once('.') {
+        dialog = dialog.add_filter(extension.to_uppercase(), &[extension]);
+    }
+    match dialog.save_file() {
+        Some(path) => std::fs::write(&path, data)
+            .map_err(|e| format!("Failed to save {}: {}", path.display(), e)),
+        None => Ok(()),
+    }
+}
+
+/// Asks the user for a file with one of `extensions` with a native "Open" dialog
+/// and returns its name and contents, or `None` if the dialog is cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_file_from_rust(
+    filter_name: &str,
+    extensions: &[&str],
+) -> Result<Option<(String, Vec<u8>)>, String> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter(filter_name, extensions)
+        .pick_file()
+    else {
+        return Ok(None);
+    };
+    match std::fs::read(&path) {
+        Ok(bytes) => Ok(Some((file_name_of(&path), bytes))),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+pub fn file_name_of(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
 }
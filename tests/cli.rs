@@ -0,0 +1,132 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use common::document_with_pages;
+use lopdf::{content::Operation, Object};
+
+mod common;
+
+/// Writes a document with a heading, body text and condensed body text on each page
+/// to the test's temporary directory, and returns its path.
+fn write_document(name: &str) -> PathBuf {
+    let pages = ["Introduction", "Usage"]
+        .into_iter()
+        .map(|heading| {
+            vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 18.into()]),
+                Operation::new("Td", vec![72.into(), 720.into()]),
+                Operation::new("Tj", vec![Object::string_literal(heading)]),
+                Operation::new("Tf", vec!["F2".into(), 10.into()]),
+                Operation::new("Td", vec![0.into(), (-30).into()]),
+                Operation::new("Tj", vec![Object::string_literal("Body text")]),
+                Operation::new("Tz", vec![50.into()]),
+                Operation::new("Td", vec![0.into(), (-14).into()]),
+                Operation::new("Tj", vec![Object::string_literal("Condensed text")]),
+                Operation::new("ET", vec![]),
+            ]
+        })
+        .collect();
+    let mut doc = document_with_pages(&["Helvetica-Bold", "Helvetica"], pages);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    doc.save(&path).unwrap();
+    path
+}
+
+fn pdf_outline(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pdf-outline"))
+        .args(args)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn listed_fonts_are_accepted_as_levels() {
+    let path = write_document("listed_fonts.pdf");
+    let path = path.to_str().unwrap();
+    let output = pdf_outline(&[path, "--list-fonts"]);
+    assert!(output.status.success());
+    let listing = String::from_utf8(output.stdout).unwrap();
+    let fonts: Vec<&str> = listing
+        .lines()
+        .map(|line| line.split('\t').next().unwrap())
+        .collect();
+    assert_eq!(
+        fonts,
+        ["Helvetica-Bold:18", "Helvetica:10", "Helvetica:5,10"]
+    );
+
+    let expected = [
+        "Introduction\t1\nUsage\t2\n",
+        "Body text\t1\nBody text\t2\n",
+        "Condensed text\t1\nCondensed text\t2\n",
+    ];
+    for (font, expected) in fonts.iter().zip(expected) {
+        let output = pdf_outline(&[path, "--level", font, "--print"]);
+        assert!(output.status.success(), "{}", font);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    }
+}
+
+#[test]
+fn unknown_heading_fonts_write_nothing() {
+    let path = write_document("unknown_fonts.pdf");
+    let output_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("unknown_fonts_out.pdf");
+    let _ = std::fs::remove_file(&output_path);
+    let output = pdf_outline(&[
+        path.to_str().unwrap(),
+        "--level",
+        "Helvetica-Bld:18",
+        "-o",
+        output_path.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "error: none of the heading fonts were found in the document\n"
+    );
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    let usage_errors: [&[&str]; 10] = [
+        &[],
+        &["in.pdf", "--unknown"],
+        &["in.pdf", "--level"],
+        &["in.pdf", "--level", "Helvetica", "--print"],
+        &["in.pdf", "--level", "Helvetica:NaN", "--print"],
+        &["in.pdf", "--suggest", "none", "--print"],
+        &["in.pdf", "--print"],
+        &["in.pdf", "--suggest", "1"],
+        &["in.pdf", "--suggest", "1", "--mode", "prepend", "--print"],
+        &["in.pdf", "--suggest", "1", "--export", "outline.doc"],
+    ];
+    for args in usage_errors {
+        let output = pdf_outline(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8(output.stderr)
+            .unwrap()
+            .starts_with("error: "));
+    }
+}
+
+#[test]
+fn processing_errors_exit_with_1() {
+    let missing = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("missing.pdf");
+    let output = pdf_outline(&[missing.to_str().unwrap(), "--list-fonts"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn help_exits_with_0() {
+    let output = pdf_outline(&["--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Usage:"));
+}
@@ -0,0 +1,64 @@
+//not every test uses every builder
+#![allow(dead_code)]
+
+use lopdf::{
+    content::{Content, Operation},
+    dictionary, Document, Object, Stream,
+};
+
+/// Builds an A4 document with a page for each content. The Type1 fonts in `fonts`
+/// are defined as `F1`, `F2` and so on in resources every page inherits.
+pub fn document_with_pages(fonts: &[&str], pages: Vec<Vec<Operation>>) -> Document {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let mut font_resources = dictionary! {};
+    for (i, base_font) in fonts.iter().enumerate() {
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => *base_font,
+        });
+        font_resources.set(format!("F{}", i + 1), font_id);
+    }
+    let resources_id = doc.add_object(dictionary! { "Font" => font_resources });
+
+    let mut kids: Vec<Object> = vec![];
+    for operations in pages {
+        let content = Content { operations };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        kids.push(page_id.into());
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+/// Content showing `heading` near the top of the page in `F1` at 18 points.
+pub fn heading(heading: &str) -> Vec<Operation> {
+    vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), 18.into()]),
+        Operation::new("Td", vec![72.into(), 720.into()]),
+        Operation::new("Tj", vec![Object::string_literal(heading)]),
+        Operation::new("ET", vec![]),
+    ]
+}
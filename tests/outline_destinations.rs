@@ -0,0 +1,163 @@
+use automatic_pdf_outline_generator::pdf_tools::{
+    pdf_font::PdfFont, read_pages, FontDecoders, InsertMode, PageText, PdfFontReader,
+    PdfOutlineGenerator, PdfOutlineInserter, PdfTextIndex,
+};
+use common::{document_with_pages, heading};
+use lopdf::{content::Operation, Document, Object, ObjectId};
+
+mod common;
+
+/// Builds a document with a heading in Helvetica-Bold on every page.
+fn document_with_headings(headings: &[&str]) -> Document {
+    let pages = headings.iter().map(|text| heading(text)).collect();
+    document_with_pages(&["Helvetica-Bold"], pages)
+}
+
+/// Returns the `Dest` of every outline item reachable through `First` and `Next`.
+fn outline_destinations(doc: &Document, first: Option<ObjectId>) -> Vec<Vec<Object>> {
+    let mut destinations = vec![];
+    let mut next = first;
+    while let Some(id) = next {
+        let item = doc.get_dictionary(id).unwrap();
+        destinations.push(item.get(b"Dest").unwrap().as_array().unwrap().clone());
+        let first_child = item.get(b"First").and_then(Object::as_reference).ok();
+        destinations.extend(outline_destinations(doc, first_child));
+        next = item.get(b"Next").and_then(Object::as_reference).ok();
+    }
+    destinations
+}
+
+#[test]
+fn outline_destinations_resolve_to_pages() {
+    let mut doc = document_with_headings(&["Introduction", "Usage", "Reference"]);
+    let heading_fonts = [vec![PdfFont::new(
+        String::from("Helvetica-Bold"),
+        (18.0, 18.0),
+    )]];
+    let outline = doc.generate_outline(&heading_fonts).unwrap();
+    assert_eq!(outline.len(), 3);
+
+    doc.insert_outline(&outline, InsertMode::Replace).unwrap();
+    let mut data = vec![];
+    doc.save_to(&mut data).unwrap();
+
+    let saved = Document::load_mem(&data).unwrap();
+    let pages = saved.get_pages();
+    let outlines_id = saved
+        .catalog()
+        .unwrap()
+        .get(b"Outlines")
+        .and_then(Object::as_reference)
+        .unwrap();
+    let first = saved
+        .get_dictionary(outlines_id)
+        .unwrap()
+        .get(b"First")
+        .and_then(Object::as_reference)
+        .ok();
+
+    let destinations = outline_destinations(&saved, first);
+    assert_eq!(destinations.len(), outline.len());
+    for (destination, (_page_number, page_id)) in destinations.iter().zip(&pages) {
+        let target = destination[0].as_reference().unwrap();
+        assert_eq!(target, *page_id);
+        assert!(saved.get_dictionary(target).unwrap().type_is(b"Page"));
+    }
+}
+
+#[test]
+fn destinations_scroll_to_the_top_of_the_heading() {
+    //18 points high, with its baseline at (70, 540) in default user space
+    let content = vec![
+        Operation::new("q", vec![]),
+        Operation::new(
+            "cm",
+            vec![
+                1.into(),
+                0.into(),
+                0.into(),
+                1.into(),
+                0.into(),
+                (-100).into(),
+            ],
+        ),
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), 9.into()]),
+        Operation::new(
+            "Tm",
+            vec![
+                2.into(),
+                0.into(),
+                0.into(),
+                2.into(),
+                50.into(),
+                600.into(),
+            ],
+        ),
+        Operation::new("Td", vec![10.into(), 20.into()]),
+        Operation::new("Tj", vec![Object::string_literal("Heading")]),
+        Operation::new("ET", vec![]),
+        Operation::new("Q", vec![]),
+    ];
+    let mut doc = document_with_pages(&["Helvetica-Bold"], vec![content]);
+    let heading_fonts = [vec![PdfFont::new(
+        String::from("Helvetica-Bold"),
+        (18.0, 18.0),
+    )]];
+    let outline = doc.generate_outline(&heading_fonts).unwrap();
+    doc.insert_outline(&outline, InsertMode::Replace).unwrap();
+
+    let outlines_id = doc
+        .catalog()
+        .unwrap()
+        .get(b"Outlines")
+        .and_then(Object::as_reference)
+        .unwrap();
+    let first = doc
+        .get_dictionary(outlines_id)
+        .unwrap()
+        .get(b"First")
+        .and_then(Object::as_reference)
+        .ok();
+    let destinations = outline_destinations(&doc, first);
+    assert_eq!(destinations.len(), 1);
+    let [page, kind, left, top, zoom] = &destinations[0][..] else {
+        panic!("expected [page /XYZ left top zoom], found {:?}", destinations[0]);
+    };
+    assert_eq!(page.as_reference().unwrap(), doc.get_pages()[&1]);
+    assert_eq!(kind.as_name().unwrap(), b"XYZ");
+    assert_eq!(left.as_float().unwrap(), 70.0);
+    assert_eq!(top.as_float().unwrap(), 558.0);
+    assert!(matches!(zoom, Object::Null));
+}
+
+#[test]
+fn pages_read_together_match_pages_read_one_by_one() {
+    let headings: Vec<String> = (1..=40).map(|i| format!("Chapter {}", i)).collect();
+    let headings: Vec<&str> = headings.iter().map(String::as_str).collect();
+    let doc = document_with_headings(&headings);
+    let pages: Vec<_> = doc.get_pages().into_iter().collect();
+
+    let together = PdfTextIndex {
+        pages: read_pages(&doc, &pages, &FontDecoders::default()),
+    };
+    let one_by_one = PdfTextIndex {
+        pages: pages
+            .iter()
+            .map(|&(page_number, page_id)| {
+                PageText::new(&doc, page_number, page_id, &FontDecoders::default())
+            })
+            .collect(),
+    };
+
+    let fonts = together.get_all_fonts().unwrap();
+    assert_eq!(fonts, one_by_one.get_all_fonts().unwrap());
+    let heading_fonts: Vec<Vec<PdfFont>> = fonts.into_keys().map(|font| vec![font]).collect();
+    let outline = together.generate_outline(&heading_fonts).unwrap();
+    assert_eq!(
+        outline,
+        one_by_one.generate_outline(&heading_fonts).unwrap()
+    );
+    let titles: Vec<&str> = outline.iter().map(|entry| entry.title.as_str()).collect();
+    assert_eq!(titles, headings);
+}
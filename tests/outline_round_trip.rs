@@ -0,0 +1,237 @@
+use automatic_pdf_outline_generator::pdf_tools::{
+    pdf_outline::{PdfOutline, PdfOutlineEntry},
+    InsertMode, PdfOutlineInserter, PdfOutlineReader,
+};
+use common::document_with_pages;
+use lopdf::{dictionary, Document, Object, ObjectId};
+
+mod common;
+
+/// Builds a document with empty pages.
+fn document_with_empty_pages(page_count: usize) -> Document {
+    document_with_pages(&[], vec![vec![]; page_count])
+}
+
+fn save_and_load(doc: &mut Document) -> Document {
+    let mut data = vec![];
+    doc.save_to(&mut data).unwrap();
+    Document::load_mem(&data).unwrap()
+}
+
+#[test]
+fn non_ascii_titles_round_trip() {
+    let mut doc = document_with_empty_pages(2);
+    let mut chapter = PdfOutlineEntry::new(1, String::from("Café – ü"));
+    chapter.position = Some((72.0, 720.0));
+    chapter
+        .children
+        .push(PdfOutlineEntry::new(2, String::from("日本語")));
+    let outline: PdfOutline = vec![chapter, PdfOutlineEntry::new(2, String::from("Plain"))];
+
+    doc.insert_outline(&outline, InsertMode::Replace).unwrap();
+    let saved = save_and_load(&mut doc);
+    assert_eq!(saved.read_outline().unwrap(), outline);
+}
+
+#[test]
+fn named_destinations_and_goto_actions_round_trip() {
+    let mut doc = document_with_empty_pages(3);
+    let pages = doc.get_pages();
+    let page = |number: u32| -> ObjectId { pages[&number] };
+
+    let outlines_id = doc.new_object_id();
+    let named_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal("Named"),
+        "Parent" => outlines_id,
+        "Dest" => Object::Name(b"chapter2".to_vec()),
+    });
+    let action_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal("Action"),
+        "Parent" => outlines_id,
+        "Prev" => named_id,
+        "A" => dictionary! {
+            "S" => "GoTo",
+            "D" => vec![page(3).into(), "Fit".into()],
+        },
+    });
+    doc.get_dictionary_mut(named_id)
+        .unwrap()
+        .set("Next", action_id);
+    doc.objects.insert(
+        outlines_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => named_id,
+            "Last" => action_id,
+            "Count" => 2,
+        }),
+    );
+    let catalog = doc.catalog_mut().unwrap();
+    catalog.set("Outlines", outlines_id);
+    catalog.set(
+        "Dests",
+        dictionary! {
+            "chapter2" => vec![page(2).into(), "XYZ".into(), 10.into(), 500.into(), Object::Null],
+        },
+    );
+
+    let saved = save_and_load(&mut doc);
+    let mut named = PdfOutlineEntry::new(2, String::from("Named"));
+    named.position = Some((10.0, 500.0));
+    let action = PdfOutlineEntry::new(3, String::from("Action"));
+    assert_eq!(saved.read_outline().unwrap(), vec![named, action]);
+}
+
+/// An outline with a top-level entry on each page, titled with a prefix and the page number.
+fn outline_on_pages(prefix: &str, page_numbers: &[u32]) -> PdfOutline {
+    page_numbers
+        .iter()
+        .map(|&page_number| PdfOutlineEntry::new(page_number, format!("{}{}", prefix, page_number)))
+        .collect()
+}
+
+fn top_level_titles(doc: &Document) -> Vec<String> {
+    let outline = doc.read_outline().unwrap();
+    outline.into_iter().map(|entry| entry.title).collect()
+}
+
+fn reference(dict: &lopdf::Dictionary, key: &[u8]) -> Option<ObjectId> {
+    dict.get(key).and_then(Object::as_reference).ok()
+}
+
+/// Checks that the children of `parent_id` are linked both ways and that every item's
+/// `Count` matches its children. Returns the number of items below `parent_id` that are
+/// visible when it is open.
+fn check_links(doc: &Document, parent_id: ObjectId) -> i64 {
+    let parent = doc.get_dictionary(parent_id).unwrap();
+    let mut children = vec![];
+    let mut next = reference(parent, b"First");
+    while let Some(id) = next {
+        let item = doc.get_dictionary(id).unwrap();
+        assert_eq!(reference(item, b"Parent"), Some(parent_id));
+        assert_eq!(reference(item, b"Prev"), children.last().copied());
+        children.push(id);
+        next = reference(item, b"Next");
+    }
+    assert_eq!(reference(parent, b"Last"), children.last().copied());
+
+    let mut visible = 0;
+    for &id in &children {
+        let below = check_links(doc, id);
+        let count = doc
+            .get_dictionary(id)
+            .unwrap()
+            .get(b"Count")
+            .and_then(Object::as_i64)
+            .unwrap_or(0);
+        //a closed item's count is negative, and its descendants aren't visible
+        assert_eq!(count.abs(), below);
+        visible += 1 + count.max(0);
+    }
+    visible
+}
+
+/// Checks that the catalog has one outline, and that it is consistent.
+fn check_outline(doc: &Document) {
+    let outline_roots = doc
+        .objects
+        .values()
+        .filter(|object| {
+            object
+                .as_dict()
+                .map_or(false, |dict| dict.type_is(b"Outlines"))
+        })
+        .count();
+    assert_eq!(outline_roots, 1);
+    let outlines_id = reference(doc.catalog().unwrap(), b"Outlines").unwrap();
+    let visible = check_links(doc, outlines_id);
+    let count = doc
+        .get_dictionary(outlines_id)
+        .unwrap()
+        .get(b"Count")
+        .and_then(Object::as_i64)
+        .unwrap();
+    assert_eq!(count, visible);
+}
+
+fn document_with_outline(outline: &PdfOutline) -> Document {
+    let mut doc = document_with_empty_pages(4);
+    doc.insert_outline(outline, InsertMode::Replace).unwrap();
+    doc
+}
+
+#[test]
+fn replacing_removes_the_old_outline() {
+    let mut old = outline_on_pages("Old ", &[1, 3]);
+    old[0].children = outline_on_pages("Old child ", &[2]);
+    let mut doc = document_with_outline(&old);
+    let objects_before = doc.objects.len();
+
+    doc.insert_outline(&outline_on_pages("New ", &[2, 4]), InsertMode::Replace)
+        .unwrap();
+    check_outline(&doc);
+    assert_eq!(top_level_titles(&doc), ["New 2", "New 4"]);
+    //three old items and their root are replaced by two new items and a root
+    assert_eq!(doc.objects.len(), objects_before - 1);
+    let saved = save_and_load(&mut doc);
+    check_outline(&saved);
+}
+
+#[test]
+fn appending_keeps_the_old_entries_first() {
+    let mut doc = document_with_outline(&outline_on_pages("Old ", &[3, 4]));
+
+    doc.insert_outline(&outline_on_pages("New ", &[1, 2]), InsertMode::Append)
+        .unwrap();
+    check_outline(&doc);
+    assert_eq!(top_level_titles(&doc), ["Old 3", "Old 4", "New 1", "New 2"]);
+}
+
+#[test]
+fn merging_interleaves_entries_by_page() {
+    let mut old = outline_on_pages("Old ", &[1, 3]);
+    old[1].children = outline_on_pages("Old child ", &[4]);
+    let mut doc = document_with_outline(&old);
+
+    let mut new = outline_on_pages("New ", &[1, 2, 4]);
+    new[1].children = outline_on_pages("New child ", &[2, 3]);
+    doc.insert_outline(&new, InsertMode::Merge).unwrap();
+    check_outline(&doc);
+    //existing entries come first on the same page
+    assert_eq!(
+        top_level_titles(&doc),
+        ["Old 1", "New 1", "New 2", "Old 3", "New 4"]
+    );
+    let saved = save_and_load(&mut doc);
+    check_outline(&saved);
+    let outline = saved.read_outline().unwrap();
+    assert_eq!(outline[2].children, new[1].children);
+    assert_eq!(outline[3].children, old[1].children);
+}
+
+#[test]
+fn inserting_into_a_document_without_an_outline_creates_one() {
+    for mode in [InsertMode::Replace, InsertMode::Append, InsertMode::Merge] {
+        let mut doc = document_with_empty_pages(2);
+        doc.insert_outline(&outline_on_pages("New ", &[1, 2]), mode)
+            .unwrap();
+        check_outline(&doc);
+        assert_eq!(top_level_titles(&doc), ["New 1", "New 2"]);
+    }
+}
+
+#[test]
+fn failed_inserts_leave_the_outline_alone() {
+    let old = outline_on_pages("Old ", &[1, 3]);
+    let mut doc = document_with_outline(&old);
+    let objects_before = doc.objects.len();
+
+    let mut new = outline_on_pages("New ", &[2]);
+    new[0].children = outline_on_pages("Missing ", &[5]);
+    for mode in [InsertMode::Replace, InsertMode::Append, InsertMode::Merge] {
+        assert!(doc.insert_outline(&new, mode).is_err());
+        assert_eq!(doc.objects.len(), objects_before);
+        assert_eq!(doc.read_outline().unwrap(), old);
+        check_outline(&doc);
+    }
+}
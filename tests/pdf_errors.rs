@@ -0,0 +1,197 @@
+use automatic_pdf_outline_generator::pdf_tools::{
+    load_document, PdfError, PdfFontReader, PdfOutlineGenerator, PdfTextIndex,
+};
+use common::document_with_pages;
+use lopdf::{content::Operation, dictionary, Document, Object, Stream};
+
+mod common;
+
+/// Builds a document with one page, on which only the font `F1` is defined.
+fn one_page_document(operations: Vec<Operation>) -> Document {
+    document_with_pages(&["Helvetica"], vec![operations])
+}
+
+/// Content showing text twice in the font resource `font`.
+fn text_in_font(font: &str) -> Vec<Operation> {
+    vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec![font.into(), 12.into()]),
+        Operation::new("Tj", vec![Object::string_literal("Text")]),
+        Operation::new("Tf", vec![font.into(), 12.into()]),
+        Operation::new("Tj", vec![Object::string_literal("More text")]),
+        Operation::new("ET", vec![]),
+    ]
+}
+
+#[test]
+fn missing_font_resource_is_reported_once() {
+    let doc = one_page_document(text_in_font("F2"));
+    let text_index = PdfTextIndex::new(&doc);
+    let problems: Vec<_> = text_index.problems().collect();
+    assert_eq!(problems.len(), 1);
+    assert!(matches!(
+        problems[0],
+        PdfError::MissingFontResource { page_number: 1, resource } if resource == "F2"
+    ));
+    assert!(text_index.get_all_fonts().unwrap().is_empty());
+}
+
+#[test]
+fn defined_font_has_no_problems() {
+    let doc = one_page_document(text_in_font("F1"));
+    let text_index = PdfTextIndex::new(&doc);
+    assert_eq!(text_index.problems().count(), 0);
+    assert_eq!(text_index.get_all_fonts().unwrap().len(), 1);
+}
+
+#[test]
+fn invalid_operands_end_the_page() {
+    let mut operations = text_in_font("F1");
+    operations.insert(3, Operation::new("Tm", vec![1.into()]));
+    let doc = one_page_document(operations);
+    let text_index = PdfTextIndex::new(&doc);
+    let problems: Vec<_> = text_index.problems().collect();
+    assert!(matches!(
+        problems[..],
+        [PdfError::MalformedContent { page_number: 1, .. }]
+    ));
+    //the text before the invalid operator is kept
+    let runs = &text_index.pages[0].runs;
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].text, "Text");
+}
+
+#[test]
+fn undecodable_page_does_not_stop_the_others() {
+    let mut doc = document_with_pages(&["Helvetica"], vec![text_in_font("F1"), text_in_font("F1")]);
+    let page_id = doc.get_pages()[&1];
+    let content_id = doc
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Contents")
+        .and_then(Object::as_reference)
+        .unwrap();
+    doc.objects.insert(
+        content_id,
+        Object::Stream(Stream::new(
+            dictionary! { "Filter" => "FlateDecode" },
+            b"not deflated".to_vec(),
+        )),
+    );
+
+    let text_index = PdfTextIndex::new(&doc);
+    assert!(text_index.pages[0].runs.is_empty());
+
+    let fonts = doc.get_all_fonts().unwrap();
+    let (font, stats) = fonts.iter().next().unwrap();
+    assert_eq!((stats.first_page, stats.last_page), (2, 2));
+    let outline = doc.generate_outline(&[vec![font.clone()]]).unwrap();
+    assert_eq!(outline.len(), 1);
+    assert_eq!(outline[0].page_number, 2);
+}
+
+#[test]
+fn files_without_a_pdf_header_are_not_pdfs() {
+    assert!(matches!(load_document(b""), Err(PdfError::NotPdf)));
+    assert!(matches!(
+        load_document(b"<html></html>"),
+        Err(PdfError::NotPdf)
+    ));
+}
+
+#[test]
+fn unknown_versions_are_unsupported() {
+    assert!(matches!(
+        load_document(b"%PDF-3.0\n"),
+        Err(PdfError::UnsupportedVersion(version)) if version == "3.0"
+    ));
+}
+
+#[test]
+fn damaged_pdfs_are_corrupt() {
+    assert!(matches!(
+        load_document(b"%PDF-1.7\n%%EOF"),
+        Err(PdfError::Load(_))
+    ));
+}
+
+#[test]
+fn saved_documents_load() {
+    let mut doc = one_page_document(text_in_font("F1"));
+    let mut data = vec![];
+    doc.save_to(&mut data).unwrap();
+    assert_eq!(load_document(&data).unwrap().get_pages().len(), 1);
+}
+
+/// The owner password hash of the encrypted documents, for the owner password "owner".
+const OWNER_HASH: &str = "c92422687facee686e373f10b5c7d04738053152f7e2ee30e11c69ec442576ab";
+/// The user password hashes of the empty password, for permissions -4 and -24.
+const EMPTY_PASSWORD_HASHES: [(i64, &str); 2] = [
+    (
+        -4,
+        "677717cfbe2d7f474425cf4f7262f32ee5c387ad084f3686577658e74f14c538",
+    ),
+    (
+        -24,
+        "3300e5152839d21d18f9510c6730f51c5e6a2350b93a38893a4dcecdc8c615e7",
+    ),
+];
+
+fn hex_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Saves a document with RC4 encryption, revision 2. Its content is left unencrypted,
+/// since it is only read after checking the password and permissions.
+fn encrypted_document(user_hash: Vec<u8>, permissions: i64) -> Vec<u8> {
+    let mut doc = one_page_document(text_in_font("F1"));
+    let encrypt_id = doc.add_object(dictionary! {
+        "Filter" => "Standard",
+        "V" => 1,
+        "R" => 2,
+        "O" => Object::string_literal(hex_bytes(OWNER_HASH)),
+        "U" => Object::string_literal(user_hash),
+        "P" => permissions,
+    });
+    doc.trailer.set("Encrypt", encrypt_id);
+    doc.trailer.set(
+        "ID",
+        vec![
+            Object::string_literal(vec![3; 16]),
+            Object::string_literal(vec![3; 16]),
+        ],
+    );
+    let mut data = vec![];
+    doc.save_to(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn encrypted_pdfs_need_their_password() {
+    //a user password hash that the empty password doesn't match
+    let data = encrypted_document(vec![2; 32], -4);
+    assert!(matches!(load_document(&data), Err(PdfError::Encrypted)));
+}
+
+#[test]
+fn unrestricted_pdfs_are_decrypted() {
+    let (permissions, user_hash) = EMPTY_PASSWORD_HASHES[0];
+    let data = encrypted_document(hex_bytes(user_hash), permissions);
+    let doc = load_document(&data).unwrap();
+    assert!(!doc.is_encrypted());
+    assert_eq!(doc.get_pages().len(), 1);
+}
+
+#[test]
+fn restricted_pdfs_are_refused() {
+    //printing and copying are forbidden
+    let (permissions, user_hash) = EMPTY_PASSWORD_HASHES[1];
+    let data = encrypted_document(hex_bytes(user_hash), permissions);
+    assert!(matches!(
+        load_document(&data),
+        Err(PdfError::Restricted(restrictions)) if restrictions == ["printing", "copying"]
+    ));
+}